@@ -1,5 +1,3 @@
-use nefsm;
-
 use nefsm::sync::EventHandler;
 use nefsm::sync::FsmEnum;
 use nefsm::sync::Response;
@@ -31,7 +29,7 @@ pub struct Ready {}
 pub struct GlobalStateTransitionHandler;
 
 impl EventHandler<State, Context, Event> for GlobalStateTransitionHandler {
-    fn on_event(&mut self, event: &Event, context: &mut Context) -> Response<State> {
+    fn on_event(&mut self, event: &Event, _context: &mut Context) -> Response<State> {
         match event {
             Event::Started => {
                 println!("Global state transition handler: Started event received");
@@ -61,9 +59,9 @@ impl EventHandler<State, Context, Event> for GlobalStateTransitionHandler {
 //         todo!()
 //     }
 // }
-impl ToString for State {
-    fn to_string(&self) -> String {
-        stringify!(self).to_owned()
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
     }
 }
 
@@ -73,9 +71,9 @@ pub enum Event {
     Disconnected,
 }
 
-impl ToString for Event {
-    fn to_string(&self) -> String {
-        stringify!(self).to_owned()
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
     }
 }
 
@@ -85,12 +83,12 @@ impl nefsm::sync::Stateful<State, Context, Event> for Null {
         nefsm::sync::Response::Transition(State::Starting)
     }
 
-    fn on_event(&mut self, event: &Event, context: &mut Context) -> nefsm::sync::Response<State> {
+    fn on_event(&mut self, event: &Event, _context: &mut Context) -> nefsm::sync::Response<State> {
         println!("Null state on event : {:?}", event);
         nefsm::sync::Response::Transition(State::Starting)
     }
 
-    fn on_exit(&mut self, context: &mut Context) {
+    fn on_exit(&mut self, _context: &mut Context) {
         println!("Null state on exit");
     }
 }
@@ -98,11 +96,11 @@ impl nefsm::sync::Stateful<State, Context, Event> for Null {
 impl nefsm::sync::Stateful<State, Context, Event> for Starting {
     fn on_enter(&mut self, context: &mut Context) -> nefsm::sync::Response<State> {
         println!("Starting state on enter");
-        context.retries = context.retries + 1;
+        context.retries += 1;
         nefsm::sync::Response::Handled
     }
 
-    fn on_event(&mut self, event: &Event, context: &mut Context) -> nefsm::sync::Response<State> {
+    fn on_event(&mut self, event: &Event, _context: &mut Context) -> nefsm::sync::Response<State> {
         println!("Starting state on event : {:?}", event);
         match event {
             Event::Started => nefsm::sync::Response::Transition(State::Ready),
@@ -110,18 +108,18 @@ impl nefsm::sync::Stateful<State, Context, Event> for Starting {
         }
     }
 
-    fn on_exit(&mut self, context: &mut Context) {
+    fn on_exit(&mut self, _context: &mut Context) {
         println!("Starting state on exit");
     }
 }
 
 impl nefsm::sync::Stateful<State, Context, Event> for Ready {
-    fn on_enter(&mut self, context: &mut Context) -> nefsm::sync::Response<State> {
+    fn on_enter(&mut self, _context: &mut Context) -> nefsm::sync::Response<State> {
         println!("Ready state on enter");
         nefsm::sync::Response::Handled
     }
 
-    fn on_event(&mut self, event: &Event, context: &mut Context) -> nefsm::sync::Response<State> {
+    fn on_event(&mut self, event: &Event, _context: &mut Context) -> nefsm::sync::Response<State> {
         println!("Ready state on event : {:?}", event);
         match event {
             Event::Disconnected => nefsm::sync::Response::Transition(State::Null),
@@ -129,7 +127,7 @@ impl nefsm::sync::Stateful<State, Context, Event> for Ready {
         }
     }
 
-    fn on_exit(&mut self, context: &mut Context) {
+    fn on_exit(&mut self, _context: &mut Context) {
         println!("Ready state on exit");
     }
 }
@@ -145,7 +143,7 @@ fn main() {
         Some(Box::new(GlobalStateTransitionHandler {})),
     );
 
-    state_machine.init(State::Null);
+    state_machine.init(State::Null).unwrap();
 
     let events = [
         Event::Started,
@@ -1,8 +1,7 @@
 // Import the async state machine module and dependencies
 use async_trait::async_trait;
-use nefsm::Async::{self, FsmEnum, Response, Stateful};
+use nefsm::Async::{FsmEnum, Response, Stateful};
 use std::fmt::Debug;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
 
 // Define the states for the telecom call
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -41,6 +40,7 @@ impl FsmEnum<CallState, CallContext, CallEvent> for CallState {
 }
 
 // Define the CallContext struct to store the number of retries
+#[derive(Default)]
 pub struct CallContext {
     pub retries: u32,
 }
@@ -198,27 +198,20 @@ impl Stateful<CallState, CallContext, CallEvent> for DisconnectedState {
     }
 }
 
+use nefsm::actor::StateMachineHandle;
 use nefsm::Async::StateMachine;
 
-async fn event_generator(sender: Sender<CallEvent>) {
-    // Generate events and send them to the receiver
-    sender.send(CallEvent::Dial).await.unwrap();
-    sender.send(CallEvent::Reject).await.unwrap();
-    sender.send(CallEvent::Dial).await.unwrap();
-    sender.send(CallEvent::Answer).await.unwrap();
-    sender.send(CallEvent::HangUp).await.unwrap();
-}
+async fn event_generator(handle: StateMachineHandle<CallState, CallEvent>) {
+    // Generate events and send them to the state machine task
+    handle.send(CallEvent::Dial).await.unwrap();
+    handle.send(CallEvent::Reject).await.unwrap();
+    handle.send(CallEvent::Dial).await.unwrap();
+    handle.send(CallEvent::Answer).await.unwrap();
 
-async fn event_receiver(
-    mut call_state_machine: StateMachine<CallState, CallContext, CallEvent>,
-    mut receiver: Receiver<CallEvent>,
-) {
-    // Process events received from the event_generator
-    while let Some(event) = receiver.recv().await {
-        match call_state_machine.process_event(&event).await {
-            Ok(_) => (),
-            Err(e) => println!("error: {:?}", e),
-        };
+    // Wait for the last event to be processed and print the resulting state
+    match handle.call(CallEvent::HangUp).await {
+        Ok(state) => println!("call ended in state {:?}", state),
+        Err(e) => println!("error: {:?}", e),
     }
 }
 
@@ -226,16 +219,17 @@ async fn event_receiver(
 async fn main() {
     // Initialize the state machine
     let mut call_state_machine = StateMachine::new(CallContext { retries: 0 }, None);
-    call_state_machine.init(CallState::Idle).await;
-
-    // Create a Tokio channel for sending and receiving events
-    let (sender, receiver) = channel(100);
+    call_state_machine.init(CallState::Idle).await.unwrap();
 
-    // Spawn two Tokio tasks: one for generating events and one for processing them
-    let event_generator_handle = tokio::spawn(event_generator(sender));
-    let event_receiver_handle = tokio::spawn(event_receiver(call_state_machine, receiver));
+    // Move the state machine into its own task, driven through a cloneable handle
+    let (handle, state_machine_task) = StateMachineHandle::spawn(call_state_machine, 100);
 
-    // Wait for both tasks to complete
+    // Spawn a Tokio task generating events
+    let event_generator_handle = tokio::spawn(event_generator(handle.clone()));
     event_generator_handle.await.unwrap();
-    event_receiver_handle.await.unwrap();
+
+    // Stop the state machine task and take the state machine back
+    handle.shutdown().await.unwrap();
+    let call_state_machine = state_machine_task.await.unwrap();
+    println!("retries: {}", call_state_machine.get_context().retries);
 }
@@ -14,6 +14,7 @@ pub enum State {
 
 #[derive(Debug)]
 pub struct Payload {
+    #[allow(dead_code)]
     f1: u16,
 }
 
@@ -38,11 +39,11 @@ struct StateC {
 #[async_trait]
 impl Stateful<State, Context, Event> for StateA {
     async fn on_enter(&mut self, context: &mut Context) -> Response<State> {
-        context.retries = context.retries + 1;
+        context.retries += 1;
         Response::Handled
     }
 
-    async fn on_event(&mut self, event: &Event, context: &mut Context) -> Response<State> {
+    async fn on_event(&mut self, event: &Event, _context: &mut Context) -> Response<State> {
         match event {
             Event::E1 => Response::Transition(State::StateB),
             _ => Response::Transition(State::StateC),
@@ -57,11 +58,11 @@ impl Stateful<State, Context, Event> for StateA {
 #[async_trait]
 impl Stateful<State, Context, Event> for StateB {
     async fn on_enter(&mut self, context: &mut Context) -> Response<State> {
-        context.retries = context.retries - 1;
+        context.retries -= 1;
         Response::Handled
     }
 
-    async fn on_event(&mut self, event: &Event, context: &mut Context) -> Response<State> {
+    async fn on_event(&mut self, event: &Event, _context: &mut Context) -> Response<State> {
         match event {
             Event::E1 => Response::Transition(State::StateC),
             _ => Response::Transition(State::StateA),
@@ -76,11 +77,11 @@ impl Stateful<State, Context, Event> for StateB {
 #[async_trait]
 impl Stateful<State, Context, Event> for StateC {
     async fn on_enter(&mut self, context: &mut Context) -> Response<State> {
-        context.retries = context.retries + 2;
+        context.retries += 2;
         Response::Handled
     }
 
-    async fn on_event(&mut self, event: &Event, context: &mut Context) -> Response<State> {
+    async fn on_event(&mut self, event: &Event, _context: &mut Context) -> Response<State> {
         match event {
             Event::E1 => Response::Transition(State::StateA),
             _ => Response::Transition(State::StateB),
@@ -112,7 +113,7 @@ struct GlobalEventHandler;
 
 #[async_trait]
 impl EventHandler<State, Context, Event> for GlobalEventHandler {
-    async fn on_event(&mut self, event: &Event, context: &mut Context) -> Response<State> {
+    async fn on_event(&mut self, event: &Event, _context: &mut Context) -> Response<State> {
         match event {
             Event::E4 => {
                 println!("Global event handler: E4 received");
@@ -178,7 +179,7 @@ async fn main() {
     });
 
     let mut state_machine = StateMachine::<State, Context, Event>::new(Context { retries: 0 }, Some(Box::new(GlobalEventHandler)));
-    state_machine.init(State::StateA).await;    
+    state_machine.init(State::StateA).await.unwrap();    
 
     let consumer = task::spawn(async move {
         while let Some(message) = rx.recv().await {
@@ -188,7 +189,9 @@ async fn main() {
                 message,
                 state_machine.get_context()
             );
-            state_machine.process_event(&message).await;
+            if let Err(e) = state_machine.process_event(&message).await {
+                println!("error: {:?}", e);
+            }
         }
     });
 
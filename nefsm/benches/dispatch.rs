@@ -0,0 +1,195 @@
+// Compare the cost of processing events with sync::StateMachine and StaticStateMachine, on a
+// packet framer reading a stream of bytes: a sync byte, a length byte, then that many payload bytes.
+// Run with `cargo bench -p nefsm`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use nefsm::static_dispatch::{StateEnum, StaticStateMachine};
+use nefsm::sync::{FsmEnum, Response, StateMachine, Stateful};
+
+const SYNC: u8 = 0xAA;
+const PACKETS: usize = 20_000;
+const ROUNDS: usize = 5;
+
+#[derive(Debug)]
+enum Input {
+    Byte(u8),
+}
+
+// Define the framer for sync::StateMachine; the payload countdown lives in the context
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+enum FramerState {
+    WaitSync,
+    Length,
+    Payload,
+}
+
+#[derive(Default)]
+struct Counters {
+    remaining: u8,
+    packets: usize,
+}
+
+impl FsmEnum<FramerState, Counters, Input> for FramerState {
+    fn create(enum_value: &FramerState) -> Box<dyn Stateful<FramerState, Counters, Input> + Send> {
+        match enum_value {
+            FramerState::WaitSync => Box::new(WaitSync),
+            FramerState::Length => Box::new(Length),
+            FramerState::Payload => Box::new(Payload),
+        }
+    }
+}
+
+struct WaitSync;
+
+impl Stateful<FramerState, Counters, Input> for WaitSync {
+    fn on_enter(&mut self, _context: &mut Counters) -> Response<FramerState> {
+        Response::Handled
+    }
+
+    fn on_event(&mut self, event: &Input, _context: &mut Counters) -> Response<FramerState> {
+        match event {
+            Input::Byte(SYNC) => Response::Transition(FramerState::Length),
+            Input::Byte(_) => Response::Handled,
+        }
+    }
+
+    fn on_exit(&mut self, _context: &mut Counters) {}
+}
+
+struct Length;
+
+impl Stateful<FramerState, Counters, Input> for Length {
+    fn on_enter(&mut self, _context: &mut Counters) -> Response<FramerState> {
+        Response::Handled
+    }
+
+    fn on_event(&mut self, event: &Input, context: &mut Counters) -> Response<FramerState> {
+        let Input::Byte(length) = event;
+        context.remaining = *length;
+        Response::Transition(FramerState::Payload)
+    }
+
+    fn on_exit(&mut self, _context: &mut Counters) {}
+}
+
+struct Payload;
+
+impl Stateful<FramerState, Counters, Input> for Payload {
+    fn on_enter(&mut self, context: &mut Counters) -> Response<FramerState> {
+        if context.remaining == 0 {
+            context.packets += 1;
+            Response::Transition(FramerState::WaitSync)
+        } else {
+            Response::Handled
+        }
+    }
+
+    fn on_event(&mut self, _event: &Input, context: &mut Counters) -> Response<FramerState> {
+        context.remaining -= 1;
+        if context.remaining == 0 {
+            context.packets += 1;
+            Response::Transition(FramerState::WaitSync)
+        } else {
+            Response::Handled
+        }
+    }
+
+    fn on_exit(&mut self, _context: &mut Counters) {}
+}
+
+// Define the same framer for StaticStateMachine; the payload countdown lives in the state
+#[derive(Debug)]
+enum Framer {
+    WaitSync,
+    Length,
+    Payload { remaining: u8 },
+}
+
+impl StateEnum<usize, Input, String> for Framer {
+    fn on_enter(&mut self, packets: &mut usize) -> Response<Framer> {
+        match self {
+            Framer::Payload { remaining: 0 } => {
+                *packets += 1;
+                Response::Transition(Framer::WaitSync)
+            }
+            _ => Response::Handled,
+        }
+    }
+
+    fn on_event(&mut self, event: &Input, packets: &mut usize) -> Response<Framer> {
+        let Input::Byte(byte) = *event;
+        match self {
+            Framer::WaitSync if byte == SYNC => Response::Transition(Framer::Length),
+            Framer::WaitSync => Response::Handled,
+            Framer::Length => Response::Transition(Framer::Payload { remaining: byte }),
+            Framer::Payload { remaining } => {
+                *remaining -= 1;
+                if *remaining == 0 {
+                    *packets += 1;
+                    Response::Transition(Framer::WaitSync)
+                } else {
+                    Response::Handled
+                }
+            }
+        }
+    }
+
+    fn on_exit(&mut self, _packets: &mut usize) {}
+}
+
+// Define a helper building a stream of packets with payloads of 0 to 31 bytes
+fn stream() -> Vec<Input> {
+    let mut bytes = Vec::new();
+    for packet in 0..PACKETS {
+        let length = (packet % 32) as u8;
+        bytes.push(SYNC);
+        bytes.push(length);
+        bytes.extend((0..length).map(|i| i.wrapping_mul(7)));
+    }
+    bytes.into_iter().map(Input::Byte).collect()
+}
+
+// Define a helper running `process` ROUNDS times and returning the fastest round
+fn measure(mut process: impl FnMut() -> usize) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let started = Instant::now();
+            assert_eq!(black_box(process()), PACKETS);
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let events = stream();
+
+    let dynamic = measure(|| {
+        let mut sm = StateMachine::new(Counters::default(), None);
+        sm.init(FramerState::WaitSync).unwrap();
+        for event in &events {
+            sm.process_event(black_box(event)).unwrap();
+        }
+        sm.get_context().packets
+    });
+
+    let fixed = measure(|| {
+        let mut sm = StaticStateMachine::new(0);
+        sm.init(Framer::WaitSync).unwrap();
+        for event in &events {
+            sm.process_event(black_box(event)).unwrap();
+        }
+        *sm.get_context()
+    });
+
+    let per_event = |elapsed: Duration| elapsed.as_nanos() as f64 / events.len() as f64;
+    println!("{} events, best of {} rounds", events.len(), ROUNDS);
+    println!("sync::StateMachine:  {:>8.1} ns/event", per_event(dynamic));
+    println!("StaticStateMachine:  {:>8.1} ns/event", per_event(fixed));
+    println!(
+        "speedup:             {:>8.1}x",
+        dynamic.as_secs_f64() / fixed.as_secs_f64()
+    );
+}
@@ -0,0 +1,155 @@
+//! Actor-style driver for `Async::StateMachine`, available with the `tokio` feature.
+//!
+//! `StateMachineHandle::spawn` moves an initialized machine into a tokio task that processes commands
+//! one at a time, and delivers the machine's timer events as they fall due. Unless the machine was given
+//! a clock with `with_clock`, the task times them with a `TokioClock`. Handles are cheap to clone
+//! and can be shared between tasks. The task stops on `shutdown` or once every handle is dropped, and
+//! hands the machine back through its `JoinHandle`.
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::timer::TokioClock;
+use crate::Async::{Error, ErrorKind, FsmEnum, StateMachine};
+
+// Define the Command enum, which carries requests from the handles to the task
+enum Command<S, E, Err> {
+    Send(E),
+    Call(E, oneshot::Sender<Result<S, Error<S, Err>>>),
+    CurrentState(oneshot::Sender<Option<S>>),
+    Shutdown(oneshot::Sender<()>),
+}
+
+// Define the StateMachineHandle struct, which talks to a machine running in its own task
+pub struct StateMachineHandle<S, E, Err = String> {
+    sender: mpsc::Sender<Command<S, E, Err>>,
+}
+
+impl<S, E, Err> Clone for StateMachineHandle<S, E, Err> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<S, E, Err> StateMachineHandle<S, E, Err>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug + Send + Sync + 'static,
+    E: Debug + Send + Sync + 'static,
+    Err: Debug + Display + Send + 'static,
+{
+    // Define a constructor spawning the task that owns `machine`, with room for `capacity` queued commands
+    pub fn spawn<CTX>(
+        mut machine: StateMachine<S, CTX, E, Err>,
+        capacity: usize,
+    ) -> (Self, JoinHandle<StateMachine<S, CTX, E, Err>>)
+    where
+        S: FsmEnum<S, CTX, E, Err>,
+        CTX: Send + 'static,
+    {
+        if !machine.has_clock() {
+            machine.set_clock(Arc::new(TokioClock::new()));
+        }
+        let (sender, receiver) = mpsc::channel(capacity);
+        let task = tokio::spawn(run(machine, receiver));
+        (Self { sender }, task)
+    }
+
+    // Define a method to queue an event without waiting for it to be processed.
+    // Processing errors are reported through tracing.
+    pub async fn send(&self, event: E) -> Result<(), Error<S, Err>> {
+        self.request(Command::Send(event)).await
+    }
+
+    // Define a method to process an event and get the resulting state
+    pub async fn call(&self, event: E) -> Result<S, Error<S, Err>> {
+        let (reply, response) = oneshot::channel();
+        self.request(Command::Call(event, reply)).await?;
+        response.await.map_err(|_| stopped())?
+    }
+
+    // Define a method to get the current state of the machine
+    pub async fn current_state(&self) -> Result<Option<S>, Error<S, Err>> {
+        let (reply, response) = oneshot::channel();
+        self.request(Command::CurrentState(reply)).await?;
+        response.await.map_err(|_| stopped())
+    }
+
+    // Define a method to stop the task once the commands queued before this one are processed
+    pub async fn shutdown(&self) -> Result<(), Error<S, Err>> {
+        let (reply, response) = oneshot::channel();
+        self.request(Command::Shutdown(reply)).await?;
+        response.await.map_err(|_| stopped())
+    }
+
+    async fn request(&self, command: Command<S, E, Err>) -> Result<(), Error<S, Err>> {
+        self.sender.send(command).await.map_err(|_| stopped())
+    }
+}
+
+// Define the task body, which owns the machine until shutdown
+async fn run<S, CTX, E, Err>(
+    mut machine: StateMachine<S, CTX, E, Err>,
+    mut receiver: mpsc::Receiver<Command<S, E, Err>>,
+) -> StateMachine<S, CTX, E, Err>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug + FsmEnum<S, CTX, E, Err>,
+    E: Debug,
+    Err: Debug + Display,
+{
+    // The sleep until the earliest timer is kept across commands, and only replaced when that timer changes
+    let mut deadline = machine.next_timer_deadline();
+    let mut next_timer = machine.next_timer();
+    loop {
+        let command = tokio::select! {
+            command = receiver.recv() => command,
+            _ = &mut next_timer => {
+                if let Err(e) = machine.fire_due_timers().await {
+                    tracing::warn!(error = ?e, "timer event failed");
+                }
+                deadline = machine.next_timer_deadline();
+                next_timer = machine.next_timer();
+                continue;
+            }
+        };
+
+        match command {
+            Some(Command::Send(event)) => {
+                if let Err(e) = machine.process_event(&event).await {
+                    tracing::warn!(event = ?event, error = ?e, "event failed");
+                }
+            }
+            Some(Command::Call(event, reply)) => {
+                let result = machine.process_event(&event).await.and_then(|_| {
+                    machine
+                        .get_current_state()
+                        .cloned()
+                        .ok_or(Error::new(ErrorKind::StateMachineNotInitialized))
+                });
+                let _ = reply.send(result);
+            }
+            Some(Command::CurrentState(reply)) => {
+                let _ = reply.send(machine.get_current_state().cloned());
+            }
+            Some(Command::Shutdown(reply)) => {
+                let _ = reply.send(());
+                break;
+            }
+            None => break,
+        }
+        if machine.next_timer_deadline() != deadline {
+            deadline = machine.next_timer_deadline();
+            next_timer = machine.next_timer();
+        }
+    }
+    machine
+}
+
+fn stopped<S, Err>() -> Error<S, Err> {
+    Error::new(ErrorKind::InternalError("state machine task has stopped"))
+}
@@ -0,0 +1,290 @@
+//! Static analysis of a transition table.
+//!
+//! An `Analyzer` walks the edges of a `TransitionTable`, together with the parents of hierarchical
+//! states and the transitions the states request from `on_enter`, neither of which the table knows
+//! about. It reports states that cannot be reached from the initial state, reachable states that
+//! cannot be left, events no edge handles, edges shadowed or made ambiguous by an unguarded edge
+//! declared before them, and cycles of `on_enter` transitions, which would keep the machine entering
+//! states forever. `Analysis::is_clean` makes it easy to fail a unit test on any of these.
+
+use std::fmt::{self, Debug, Display};
+
+use crate::table::{event_name, TransitionTable};
+
+// Define the Analysis struct, which lists the problems found in a transition table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis<S> {
+    // States that no sequence of events leads to from the initial state
+    pub unreachable_states: Vec<S>,
+    // Reachable states, not declared final, with no edge or on_enter transition out of them or their ancestors
+    pub dead_end_states: Vec<S>,
+    // Events that no edge of the table handles
+    pub unhandled_events: Vec<String>,
+    // (state, event) pairs with several edges where an unguarded edge comes before another one
+    pub nondeterministic_edges: Vec<(S, String)>,
+    // Groups of states whose on_enter transitions can lead back to each other
+    pub enter_cycles: Vec<Vec<S>>,
+}
+
+impl<S> Analysis<S> {
+    // Define a method to check that no problem was found
+    pub fn is_clean(&self) -> bool {
+        self.unreachable_states.is_empty()
+            && self.dead_end_states.is_empty()
+            && self.unhandled_events.is_empty()
+            && self.nondeterministic_edges.is_empty()
+            && self.enter_cycles.is_empty()
+    }
+}
+
+impl<S: Debug> Display for Analysis<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return writeln!(f, "no problems found");
+        }
+        for state in &self.unreachable_states {
+            writeln!(f, "unreachable state: {:?}", state)?;
+        }
+        for state in &self.dead_end_states {
+            writeln!(f, "dead-end state: {:?}", state)?;
+        }
+        for event in &self.unhandled_events {
+            writeln!(f, "unhandled event: {}", event)?;
+        }
+        for (state, event) in &self.nondeterministic_edges {
+            writeln!(f, "nondeterministic edges: {:?} + {}", state, event)?;
+        }
+        for cycle in &self.enter_cycles {
+            writeln!(f, "on_enter cycle: {:?}", cycle)?;
+        }
+        Ok(())
+    }
+}
+
+// Define the Analyzer struct, which collects what the analysis needs to know beyond the table
+pub struct Analyzer<'a, S, CTX, E> {
+    table: &'a TransitionTable<S, CTX, E>,
+    initial_state: S,
+    states: Vec<S>,
+    events: Vec<String>,
+    final_states: Vec<S>,
+    enter_transitions: Vec<(S, S)>,
+    parent: fn(&S) -> Option<S>,
+}
+
+impl<'a, S: Clone + PartialEq, CTX, E: Debug> Analyzer<'a, S, CTX, E> {
+    // Define a constructor analyzing `table` for a machine initialized with `initial_state`
+    pub fn new(table: &'a TransitionTable<S, CTX, E>, initial_state: S) -> Self {
+        Self {
+            table,
+            initial_state,
+            states: Vec::new(),
+            events: Vec::new(),
+            final_states: Vec::new(),
+            enter_transitions: Vec::new(),
+            parent: |_| None,
+        }
+    }
+
+    // Define a method to list every state of the machine, so that states missing from the table are checked too
+    pub fn with_states(mut self, states: &[S]) -> Self {
+        self.states.extend_from_slice(states);
+        self
+    }
+
+    // Define a method to list the events the machine receives, one value of each variant
+    pub fn with_events(mut self, events: &[E]) -> Self {
+        self.events.extend(events.iter().map(event_name));
+        self
+    }
+
+    // Define a method to declare states meant to be terminal, which are not reported as dead ends
+    pub fn with_final_states(mut self, states: &[S]) -> Self {
+        self.final_states.extend_from_slice(states);
+        self
+    }
+
+    // Define a method to declare that the on_enter of `from` may return Response::Transition(`to`)
+    pub fn with_enter_transition(mut self, from: S, to: S) -> Self {
+        self.enter_transitions.push((from, to));
+        self
+    }
+
+    // Define a method to set the parent of each state, typically `FsmEnum::parent`
+    pub fn with_parents(mut self, parent: fn(&S) -> Option<S>) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    // Define a method to run the analysis
+    pub fn analyze(&self) -> Analysis<S> {
+        let states = self.all_states();
+        let current_states = self.current_states();
+        // Entering a state also enters its ancestors
+        let reachable: Vec<S> = current_states
+            .iter()
+            .flat_map(|state| self.ancestry(state))
+            .collect();
+
+        let unreachable_states = states
+            .iter()
+            .filter(|state| !reachable.contains(state))
+            .cloned()
+            .collect();
+
+        let dead_end_states = current_states
+            .iter()
+            .filter(|state| !self.final_states.contains(state) && self.successors(state).is_empty())
+            .cloned()
+            .collect();
+
+        let unhandled_events = self
+            .events
+            .iter()
+            .filter(|event| {
+                !self
+                    .table
+                    .get_transitions()
+                    .iter()
+                    .any(|t| t.get_event_name() == event.as_str())
+            })
+            .cloned()
+            .collect();
+
+        Analysis {
+            unreachable_states,
+            dead_end_states,
+            unhandled_events,
+            nondeterministic_edges: self.nondeterministic_edges(),
+            enter_cycles: self.enter_cycles(&states),
+        }
+    }
+
+    // Define a helper listing the declared states followed by the ones only found in the table, without duplicates
+    fn all_states(&self) -> Vec<S> {
+        let mut states = Vec::new();
+        let mentioned = self
+            .states
+            .iter()
+            .chain(std::iter::once(&self.initial_state))
+            .chain(
+                self.table
+                    .get_transitions()
+                    .iter()
+                    .flat_map(|t| [t.get_from(), t.get_to()]),
+            )
+            .chain(
+                self.enter_transitions
+                    .iter()
+                    .flat_map(|(from, to)| [from, to]),
+            );
+        for state in mentioned {
+            for state in self.ancestry(state) {
+                if !states.contains(&state) {
+                    states.push(state);
+                }
+            }
+        }
+        states
+    }
+
+    // Define a helper listing the states the machine can move to from `state`, through the table edges
+    // of the state and its ancestors, or through its own on_enter
+    fn successors(&self, state: &S) -> Vec<S> {
+        let ancestry = self.ancestry(state);
+        let edges = self
+            .table
+            .get_transitions()
+            .iter()
+            .filter(|t| ancestry.contains(t.get_from()))
+            .map(|t| t.get_to());
+        let redirects = self
+            .enter_transitions
+            .iter()
+            .filter(|(from, _)| from == state)
+            .map(|(_, to)| to);
+        edges
+            .chain(redirects)
+            .filter(|to| *to != state)
+            .cloned()
+            .collect()
+    }
+
+    // Define a helper listing the states the machine can be in, starting from the initial state
+    fn current_states(&self) -> Vec<S> {
+        let mut visited = Vec::new();
+        let mut pending = vec![self.initial_state.clone()];
+        while let Some(state) = pending.pop() {
+            if !visited.contains(&state) {
+                pending.extend(self.successors(&state));
+                visited.push(state);
+            }
+        }
+        visited
+    }
+
+    // Define a helper finding the (state, event) pairs where an unguarded edge is followed by another edge
+    fn nondeterministic_edges(&self) -> Vec<(S, String)> {
+        let transitions = self.table.get_transitions();
+        let mut found: Vec<(S, String)> = Vec::new();
+        for (index, transition) in transitions.iter().enumerate() {
+            if transition.has_guard() {
+                continue;
+            }
+            let shadows = transitions[index + 1..].iter().any(|t| {
+                t.get_from() == transition.get_from()
+                    && t.get_event_name() == transition.get_event_name()
+            });
+            let pair = (
+                transition.get_from().clone(),
+                transition.get_event_name().to_string(),
+            );
+            if shadows && !found.contains(&pair) {
+                found.push(pair);
+            }
+        }
+        found
+    }
+
+    // Define a helper grouping the states whose on_enter transitions lead back to each other.
+    // A state redirecting to itself is not a cycle: the machine just stays there.
+    fn enter_cycles(&self, states: &[S]) -> Vec<Vec<S>> {
+        let reaches = |from: &S| {
+            let mut seen: Vec<S> = Vec::new();
+            let mut pending = vec![from.clone()];
+            while let Some(state) = pending.pop() {
+                for (source, to) in &self.enter_transitions {
+                    if *source == state && *to != state && !seen.contains(to) {
+                        seen.push(to.clone());
+                        pending.push(to.clone());
+                    }
+                }
+            }
+            seen
+        };
+        let reach: Vec<Vec<S>> = states.iter().map(reaches).collect();
+        let mut cycles: Vec<Vec<S>> = Vec::new();
+        for (index, state) in states.iter().enumerate() {
+            if !reach[index].contains(state) || cycles.iter().any(|c| c.contains(state)) {
+                continue;
+            }
+            let cycle = states
+                .iter()
+                .enumerate()
+                .filter(|(other, s)| reach[index].contains(s) && reach[*other].contains(state))
+                .map(|(_, s)| s.clone())
+                .collect();
+            cycles.push(cycle);
+        }
+        cycles
+    }
+
+    // Define a helper returning a state followed by all of its ancestors, innermost first
+    fn ancestry(&self, state: &S) -> Vec<S> {
+        let mut path = vec![state.clone()];
+        while let Some(parent) = (self.parent)(path.last().unwrap()) {
+            path.push(parent);
+        }
+        path
+    }
+}
@@ -0,0 +1,20 @@
+//! Cache policies for the state objects of a `StateMachine`.
+//!
+//! A machine creates the object of a state with `FsmEnum::create` the first time the state is used.
+//! By default the object is then kept for the life of the machine, so any data it holds survives
+//! leaving and re-entering the state. `StateMachine::with_cache_policy` can instead drop the object
+//! whenever the state is exited, so it is recreated on the next entry, or only keep a bounded number
+//! of objects for inactive states, dropping the least recently exited first. The objects of the
+//! current state and its ancestors are never dropped.
+
+// Define the CachePolicy enum, which tells how long a machine keeps the state objects it created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    // Keep every state object for the life of the machine
+    #[default]
+    Forever,
+    // Drop a state object when its state is exited, so that it is recreated on every entry
+    RecreateOnEntry,
+    // Keep at most this many state objects, dropping the least recently exited ones first
+    Lru(usize),
+}
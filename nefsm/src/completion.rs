@@ -0,0 +1,80 @@
+//! Completion futures for `Async::StateMachine`.
+//!
+//! `StateMachine::completion` returns a `Completion` that resolves once the machine enters a final
+//! state (see `FsmEnum::is_final`), with that state and a clone of the context at that point. It does
+//! not borrow the machine, so it can be awaited from another task while the machine keeps running.
+//! If the machine is dropped before finishing, the future resolves to `None`.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+// Define the Shared struct, which is the slot filled by the machine and read by the future
+struct Shared<S, CTX> {
+    result: Option<(S, CTX)>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+// Define the Completion future, which resolves with the final state and context
+pub struct Completion<S, CTX> {
+    shared: Arc<Mutex<Shared<S, CTX>>>,
+}
+
+// Define the CompletionSender struct, which the machine keeps until it finishes
+pub(crate) struct CompletionSender<S, CTX> {
+    shared: Arc<Mutex<Shared<S, CTX>>>,
+}
+
+// Define a function to create a connected sender and future
+pub(crate) fn completion<S, CTX>() -> (CompletionSender<S, CTX>, Completion<S, CTX>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        closed: false,
+        waker: None,
+    }));
+    (
+        CompletionSender {
+            shared: shared.clone(),
+        },
+        Completion { shared },
+    )
+}
+
+impl<S, CTX> CompletionSender<S, CTX> {
+    // Define a method to resolve the future with the final state and context
+    pub(crate) fn complete(self, state: S, context: CTX) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.result = Some((state, context));
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<S, CTX> Drop for CompletionSender<S, CTX> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.closed = true;
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<S, CTX> Future for Completion<S, CTX> {
+    type Output = Option<(S, CTX)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(result) = shared.result.take() {
+            return Poll::Ready(Some(result));
+        }
+        if shared.closed {
+            return Poll::Ready(None);
+        }
+        shared.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
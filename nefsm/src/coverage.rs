@@ -0,0 +1,369 @@
+//! Coverage of the states, transitions and errors a test suite exercised.
+//!
+//! A `CoverageRecorder` handed to `StateMachine::with_coverage` counts the states entered, the
+//! (state, event, state) transitions fired and the (state, event) pairs whose callback returned
+//! `Response::Error`. A transition is recorded with the target it selected, even when that state's
+//! `on_enter` then moves on to another one. Recorders are cheap to clone and every clone adds to the same `Coverage`, so
+//! one recorder can follow all the machines of a test suite; separate `Coverage` values can also be
+//! merged. A `CoverageReport` compares a coverage with a declared `TransitionTable` and lists the
+//! untested edges, as text or JSON. Events are identified by their variant name, as in the table.
+
+use std::fmt::{self, Debug, Display};
+use std::sync::{Arc, Mutex};
+
+use crate::table::{event_name, TransitionTable};
+
+// Define the Coverage struct, which counts what the recorded machines did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage<S> {
+    states: Vec<(S, usize)>,
+    transitions: Vec<((S, String, S), usize)>,
+    errors: Vec<((S, String), usize)>,
+    // The parent of each nested state seen, so edges declared on a parent can be matched
+    parents: Vec<(S, S)>,
+}
+
+impl<S> Default for Coverage<S> {
+    fn default() -> Self {
+        Self {
+            states: Vec::new(),
+            transitions: Vec::new(),
+            errors: Vec::new(),
+            parents: Vec::new(),
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> Coverage<S> {
+    // Define a constructor for an empty coverage
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Define a method to get each state entered and how many times, in the order they were first entered
+    pub fn get_states(&self) -> &[(S, usize)] {
+        &self.states
+    }
+
+    // Define a method to get each (from, event, to) transition and how many times it fired. An event
+    // handled without leaving the state counts as a transition from the state to itself.
+    pub fn get_transitions(&self) -> &[((S, String, S), usize)] {
+        &self.transitions
+    }
+
+    // Define a method to get each (state, event) pair that returned Response::Error and how many times
+    pub fn get_errors(&self) -> &[((S, String), usize)] {
+        &self.errors
+    }
+
+    // Define a method to add the counts of another coverage to this one
+    pub fn merge(&mut self, other: &Coverage<S>) {
+        for (state, hits) in &other.states {
+            add(&mut self.states, state.clone(), *hits);
+        }
+        for (transition, hits) in &other.transitions {
+            add(&mut self.transitions, transition.clone(), *hits);
+        }
+        for (pair, hits) in &other.errors {
+            add(&mut self.errors, pair.clone(), *hits);
+        }
+        for pair in &other.parents {
+            if !self.parents.contains(pair) {
+                self.parents.push(pair.clone());
+            }
+        }
+    }
+
+    // Define a method to compare the coverage with the edges declared in `table`
+    pub fn report<CTX, E: Debug>(&self, table: &TransitionTable<S, CTX, E>) -> CoverageReport<S> {
+        let edges: Vec<EdgeCoverage<S>> = table
+            .get_transitions()
+            .iter()
+            .map(|t| EdgeCoverage {
+                from: t.get_from().clone(),
+                event: t.get_event_name().to_string(),
+                to: t.get_to().clone(),
+                hits: self
+                    .transitions
+                    .iter()
+                    .filter(|((from, event, to), _)| {
+                        event == t.get_event_name()
+                            && to == t.get_to()
+                            && self.ancestry(from).contains(t.get_from())
+                    })
+                    .map(|(_, hits)| hits)
+                    .sum(),
+            })
+            .collect();
+
+        let mut declared_states: Vec<S> = Vec::new();
+        for edge in &edges {
+            for state in [&edge.from, &edge.to] {
+                if !declared_states.contains(state) {
+                    declared_states.push(state.clone());
+                }
+            }
+        }
+        let unvisited_states = declared_states
+            .into_iter()
+            .filter(|state| !self.states.iter().any(|(s, _)| s == state))
+            .collect();
+
+        let undeclared_transitions = self
+            .transitions
+            .iter()
+            .filter(|((from, event, to), _)| {
+                from != to
+                    && !edges.iter().any(|edge| {
+                        edge.event == *event
+                            && edge.to == *to
+                            && self.ancestry(from).contains(&edge.from)
+                    })
+            })
+            .map(|(transition, _)| transition.clone())
+            .collect();
+
+        CoverageReport {
+            edges,
+            unvisited_states,
+            undeclared_transitions,
+            errors: self
+                .errors
+                .iter()
+                .map(|((state, event), hits)| (state.clone(), event.clone(), *hits))
+                .collect(),
+        }
+    }
+
+    // Define a helper returning a state followed by all of its ancestors, innermost first
+    fn ancestry(&self, state: &S) -> Vec<S> {
+        let mut path = vec![state.clone()];
+        while let Some((_, parent)) = self
+            .parents
+            .iter()
+            .find(|(child, _)| child == path.last().unwrap())
+        {
+            path.push(parent.clone());
+        }
+        path
+    }
+}
+
+// Define a helper adding hits to the counter of `key`, appending it if it is new
+fn add<K: PartialEq>(counters: &mut Vec<(K, usize)>, key: K, hits: usize) {
+    match counters.iter_mut().find(|(k, _)| *k == key) {
+        Some((_, count)) => *count += hits,
+        None => counters.push((key, hits)),
+    }
+}
+
+// Define the CoverageRecorder struct, a shared handle machines record their coverage through
+#[derive(Debug)]
+pub struct CoverageRecorder<S> {
+    coverage: Arc<Mutex<Coverage<S>>>,
+}
+
+impl<S> Clone for CoverageRecorder<S> {
+    fn clone(&self) -> Self {
+        Self {
+            coverage: self.coverage.clone(),
+        }
+    }
+}
+
+impl<S> Default for CoverageRecorder<S> {
+    fn default() -> Self {
+        Self {
+            coverage: Arc::new(Mutex::new(Coverage::default())),
+        }
+    }
+}
+
+impl<S: Clone + PartialEq> CoverageRecorder<S> {
+    // Define a constructor for a recorder with nothing recorded yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Define a method to get a copy of what was recorded so far
+    pub fn get_coverage(&self) -> Coverage<S> {
+        self.coverage.lock().unwrap().clone()
+    }
+
+    // Define a method to compare what was recorded so far with the edges declared in `table`
+    pub fn report<CTX, E: Debug>(&self, table: &TransitionTable<S, CTX, E>) -> CoverageReport<S> {
+        self.coverage.lock().unwrap().report(table)
+    }
+
+    // Define a method to record that a state was entered
+    pub(crate) fn record_state(&self, state: &S, parent: Option<S>) {
+        let mut coverage = self.coverage.lock().unwrap();
+        add(&mut coverage.states, state.clone(), 1);
+        if let Some(parent) = parent {
+            let pair = (state.clone(), parent);
+            if !coverage.parents.contains(&pair) {
+                coverage.parents.push(pair);
+            }
+        }
+    }
+
+    // Define a method to record that an event was processed without error
+    pub(crate) fn record_transition<E: Debug>(&self, from: &S, event: &E, to: &S) {
+        let transition = (from.clone(), event_name(event), to.clone());
+        add(
+            &mut self.coverage.lock().unwrap().transitions,
+            transition,
+            1,
+        );
+    }
+
+    // Define a method to record that processing an event returned Response::Error
+    pub(crate) fn record_error<E: Debug>(&self, state: &S, event: &E) {
+        let pair = (state.clone(), event_name(event));
+        add(&mut self.coverage.lock().unwrap().errors, pair, 1);
+    }
+}
+
+// Define the EdgeCoverage struct, which is a declared edge and how many times it fired
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdgeCoverage<S> {
+    pub from: S,
+    pub event: String,
+    pub to: S,
+    pub hits: usize,
+}
+
+// Define the CoverageReport struct, which compares a coverage with a transition table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport<S> {
+    // Every edge of the table, in declaration order; a hit count of 0 means the edge is untested
+    pub edges: Vec<EdgeCoverage<S>>,
+    // States the table mentions that were never entered
+    pub unvisited_states: Vec<S>,
+    // Transitions that fired without a table edge, typically requested by on_event or on_enter
+    pub undeclared_transitions: Vec<(S, String, S)>,
+    // (state, event) pairs that returned Response::Error, with how many times they did
+    pub errors: Vec<(S, String, usize)>,
+}
+
+impl<S> CoverageReport<S> {
+    // Define a method to list the edges that never fired
+    pub fn untested_edges(&self) -> Vec<&EdgeCoverage<S>> {
+        self.edges.iter().filter(|edge| edge.hits == 0).collect()
+    }
+
+    // Define a method to check that every edge fired and every state was entered
+    pub fn is_complete(&self) -> bool {
+        self.edges.iter().all(|edge| edge.hits > 0) && self.unvisited_states.is_empty()
+    }
+}
+
+impl<S: Debug> CoverageReport<S> {
+    // Define a method to export the report as JSON, with states written as their Debug output
+    pub fn to_json(&self) -> String {
+        let edges: Vec<String> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                format!(
+                    "{{\"from\":{},\"event\":{},\"to\":{},\"hits\":{}}}",
+                    json_string(&format!("{:?}", edge.from)),
+                    json_string(&edge.event),
+                    json_string(&format!("{:?}", edge.to)),
+                    edge.hits
+                )
+            })
+            .collect();
+        let unvisited: Vec<String> = self
+            .unvisited_states
+            .iter()
+            .map(|state| json_string(&format!("{:?}", state)))
+            .collect();
+        let undeclared: Vec<String> = self
+            .undeclared_transitions
+            .iter()
+            .map(|(from, event, to)| {
+                format!(
+                    "{{\"from\":{},\"event\":{},\"to\":{}}}",
+                    json_string(&format!("{:?}", from)),
+                    json_string(event),
+                    json_string(&format!("{:?}", to))
+                )
+            })
+            .collect();
+        let errors: Vec<String> = self
+            .errors
+            .iter()
+            .map(|(state, event, hits)| {
+                format!(
+                    "{{\"state\":{},\"event\":{},\"hits\":{}}}",
+                    json_string(&format!("{:?}", state)),
+                    json_string(event),
+                    hits
+                )
+            })
+            .collect();
+        format!(
+            "{{\"covered_edges\":{},\"total_edges\":{},\"edges\":[{}],\"unvisited_states\":[{}],\"undeclared_transitions\":[{}],\"errors\":[{}]}}",
+            self.edges.iter().filter(|edge| edge.hits > 0).count(),
+            self.edges.len(),
+            edges.join(","),
+            unvisited.join(","),
+            undeclared.join(","),
+            errors.join(",")
+        )
+    }
+}
+
+impl<S: Debug> Display for CoverageReport<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}/{} edges covered",
+            self.edges.iter().filter(|edge| edge.hits > 0).count(),
+            self.edges.len()
+        )?;
+        for edge in &self.edges {
+            let hits = match edge.hits {
+                0 => "UNTESTED".to_string(),
+                hits => format!("{} hits", hits),
+            };
+            writeln!(
+                f,
+                "  {:?} + {} => {:?}: {}",
+                edge.from, edge.event, edge.to, hits
+            )?;
+        }
+        for state in &self.unvisited_states {
+            writeln!(f, "unvisited state: {:?}", state)?;
+        }
+        for (from, event, to) in &self.undeclared_transitions {
+            writeln!(
+                f,
+                "undeclared transition: {:?} + {} => {:?}",
+                from, event, to
+            )?;
+        }
+        for (state, event, hits) in &self.errors {
+            writeln!(f, "error: {:?} + {} ({} times)", state, event, hits)?;
+        }
+        Ok(())
+    }
+}
+
+// Define a helper quoting a string for JSON
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
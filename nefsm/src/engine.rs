@@ -0,0 +1,1438 @@
+//! The state machine engine shared by the `sync` and `Async` modules.
+//!
+//! `Region` holds everything a machine keeps track of but its context, and implements the whole algorithm
+//! once: init, event dispatch through the global handler, the transition table and the state hierarchy,
+//! transitions, deferred and posted events, transactions, timers and completions. Its states run against
+//! a context lent to every call, so that several regions can share one: `StateMachine` is a region
+//! together with the context it owns, and `ParallelStateMachine` runs several regions over the context
+//! it owns. The states are reached through a `FrontEnd`, which adapts the callbacks of one flavour of
+//! `Stateful` to the engine.
+//!
+//! The engine is written as async code. The `Async` front-end awaits the futures of its callbacks,
+//! while the `sync` front-end hands back futures that are already complete, so its machines finish
+//! every operation in a single poll, without a runtime. `sync::StateMachine` and `Async::StateMachine`
+//! are this struct with their front-end, which only adds the methods that are blocking in one module
+//! and async in the other, and so are the `Region` and `ParallelStateMachine` of each module. Both
+//! modules therefore behave identically, by construction.
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::cache::CachePolicy;
+use crate::coverage::CoverageRecorder;
+use crate::error::{Error, ErrorKind};
+use crate::graph;
+use crate::history::{History, Outcome};
+use crate::observer::TransitionObserver;
+use crate::outbox::HasOutbox;
+use crate::response::Response;
+#[cfg(feature = "serde")]
+use crate::snapshot::Snapshot;
+use crate::table::TransitionTable;
+use crate::timer::{Clock, Sleep, SystemClock, Timers};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+// Define the SyncFrontEnd struct, which marks the machines of the `sync` module
+pub struct SyncFrontEnd;
+
+// Define the AsyncFrontEnd struct, which marks the machines of the `Async` module
+pub struct AsyncFrontEnd;
+
+impl sealed::Sealed for SyncFrontEnd {}
+impl sealed::Sealed for AsyncFrontEnd {}
+
+// Define the FrontEnd trait, which lets the engine call the states of one module.
+// It is implemented by `SyncFrontEnd` and `AsyncFrontEnd` only.
+pub trait FrontEnd<S, CTX, E, Err>: sealed::Sealed {
+    // The object of a state, as created by `FsmEnum::create`
+    type State: ?Sized;
+    // The global event handler
+    type Handler: ?Sized;
+
+    fn create(state: &S) -> Box<Self::State>;
+    fn parent(state: &S) -> Option<S>;
+    fn is_final(state: &S) -> bool;
+    fn on_enter<'a>(
+        state: &'a mut Self::State,
+        context: &'a mut CTX,
+    ) -> impl Future<Output = Response<S, Err>>;
+    fn on_event<'a>(
+        state: &'a mut Self::State,
+        event: &'a E,
+        context: &'a mut CTX,
+    ) -> impl Future<Output = Response<S, Err>>;
+    fn on_exit<'a>(state: &'a mut Self::State, context: &'a mut CTX) -> impl Future<Output = ()>;
+    fn on_global_event<'a>(
+        handler: &'a mut Self::Handler,
+        event: &'a E,
+        context: &'a mut CTX,
+    ) -> impl Future<Output = Response<S, Err>>;
+    fn is_deferred(state: &Self::State, event: &E, context: &CTX) -> bool;
+    fn on_context_changed(state: &mut Self::State, context: &mut CTX);
+    fn schedule_timers(state: &mut Self::State, context: &CTX, timers: &mut Timers<E>);
+    #[cfg(feature = "serde")]
+    fn save_data(state: &Self::State) -> Option<Vec<u8>>;
+    #[cfg(feature = "serde")]
+    fn restore_data(state: &mut Self::State, data: &[u8]);
+}
+
+// Define the type of the futures of a region run by a `ParallelStateMachine`
+pub type RegionFuture<'a, S, Err> =
+    Pin<Box<dyn Future<Output = Result<(), Error<S, Err>>> + Send + 'a>>;
+
+// Define the ParallelFrontEnd trait, which lets a `ParallelStateMachine` run the regions of one module
+// behind a trait object. The futures are boxed by the front-end, which knows what makes them `Send`.
+pub trait ParallelFrontEnd<S, CTX, E, Err>: FrontEnd<S, CTX, E, Err> + Sized {
+    fn init_region<'a>(
+        region: &'a mut Region<S, CTX, E, Err, Self>,
+        context: &'a mut CTX,
+        initial_state: S,
+    ) -> RegionFuture<'a, S, Err>;
+    fn process_region_event<'a>(
+        region: &'a mut Region<S, CTX, E, Err, Self>,
+        context: &'a mut CTX,
+        event: &'a E,
+    ) -> RegionFuture<'a, S, Err>;
+}
+
+// Define a function to run the engine for a sync machine. The sync front-end only returns futures that
+// are already complete, so the engine never waits and a single poll finishes it.
+pub(crate) fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let mut future = pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("sync state callbacks never wait"),
+    }
+}
+
+// Define the ScheduledTimer struct, which holds a pending timer event and the state owning it
+struct ScheduledTimer<S, E> {
+    id: u64,
+    owner: S,
+    deadline: Duration,
+    event: E,
+}
+
+// Define the type of the callbacks resolving the completion futures
+pub(crate) type CompletionCallback<S, CTX> = Box<dyn FnOnce(&S, &CTX) + Send>;
+
+// Define the Checkpoint struct, which holds what a failed transaction restores
+struct Checkpoint<S, CTX> {
+    context: CTX,
+    current_state: Option<S>,
+    next_timer_id: u64,
+}
+
+// Define the Region struct, which is a state machine running against a context it does not own.
+// Every method that runs states is given the context to run them against.
+pub struct Region<S, CTX, E, Err, F: FrontEnd<S, CTX, E, Err>> {
+    states: HashMap<S, Box<F::State>>,
+    current_state: Option<S>,
+    global_event_handler: Option<Box<F::Handler>>,
+    transition_table: TransitionTable<S, CTX, E>,
+    history: Option<History<S>>,
+    describe_error: fn(&ErrorKind<S, Err>) -> String,
+    clone_event: Option<fn(&E) -> E>,
+    clone_context: Option<fn(&CTX) -> CTX>,
+    deferred_events: Vec<E>,
+    drain_outbox: Option<fn(&mut CTX) -> VecDeque<E>>,
+    max_posted_events: usize,
+    max_enter_chain: usize,
+    cache_policy: CachePolicy,
+    // The cached objects of inactive states, least recently exited first
+    inactive_states: VecDeque<S>,
+    coverage: Option<CoverageRecorder<S>>,
+    observers: Vec<Box<dyn TransitionObserver<S, E> + Send>>,
+    clock: Arc<dyn Clock>,
+    // Whether the clock was chosen with `set_clock` rather than left to the default
+    clock_set: bool,
+    timers: Vec<ScheduledTimer<S, E>>,
+    next_timer_id: u64,
+    completions: Vec<CompletionCallback<S, CTX>>,
+    cancelled_timers: Vec<ScheduledTimer<S, E>>,
+    // The state objects dropped by the cache policy since the checkpoint, oldest first
+    evicted_states: Vec<(S, Box<F::State>)>,
+}
+
+// Define the StateMachine struct, which represents the finite state machine: a region together with
+// the context it owns
+pub struct StateMachine<S, CTX, E, Err, F: FrontEnd<S, CTX, E, Err>> {
+    region: Region<S, CTX, E, Err, F>,
+    context: CTX,
+}
+
+// Define the ParallelStateMachine struct, which runs several regions side by side over the context it owns.
+// Every event is dispatched to every region, in the order the regions were added. The context stays in
+// place while the regions run, so dropping a pending call loses nothing but the regions it did not reach.
+pub struct ParallelStateMachine<CTX, E, Err, F> {
+    regions: Vec<Box<dyn AnyRegion<CTX, E, Err>>>,
+    context: CTX,
+    _front_end: PhantomData<F>,
+}
+
+// Define the AnyRegion trait, which lets regions over different state enums run side by side
+trait AnyRegion<CTX, E, Err>: Send {
+    fn init<'a>(&'a mut self, context: &'a mut CTX) -> RegionFuture<'a, String, Err>;
+    fn process_event<'a>(
+        &'a mut self,
+        event: &'a E,
+        context: &'a mut CTX,
+    ) -> RegionFuture<'a, String, Err>;
+    fn is_finished(&self) -> bool;
+    fn state_name(&self) -> Option<String>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+// Define the InitialRegion struct, which is a region and the state it starts in
+struct InitialRegion<S, CTX, E, Err, F: FrontEnd<S, CTX, E, Err>> {
+    region: Region<S, CTX, E, Err, F>,
+    initial_state: S,
+}
+
+// Implement methods for the Region struct
+impl<S, CTX, E, Err, F> Region<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone,
+    E: Debug,
+    Err: Debug + Display,
+    F: FrontEnd<S, CTX, E, Err>,
+{
+    // Define a constructor used by the front-ends' `new`
+    pub(crate) fn create(handler: Option<Box<F::Handler>>) -> Self {
+        Self {
+            states: HashMap::new(),
+            current_state: None,
+            global_event_handler: handler,
+            transition_table: TransitionTable::new(),
+            history: None,
+            describe_error: |_| String::new(),
+            clone_event: None,
+            clone_context: None,
+            deferred_events: Vec::new(),
+            drain_outbox: None,
+            max_posted_events: 0,
+            max_enter_chain: 64,
+            cache_policy: CachePolicy::Forever,
+            inactive_states: VecDeque::new(),
+            coverage: None,
+            observers: Vec::new(),
+            clock: Arc::new(SystemClock::new()),
+            clock_set: false,
+            timers: Vec::new(),
+            next_timer_id: 0,
+            completions: Vec::new(),
+            cancelled_timers: Vec::new(),
+            evicted_states: Vec::new(),
+        }
+    }
+
+    // Define a method to attach a transition table, consulted before the states' own on_event
+    pub fn with_transitions(mut self, table: TransitionTable<S, CTX, E>) -> Self {
+        self.transition_table = table;
+        self
+    }
+
+    // Define a method to record the last `capacity` processed events in a history ring buffer
+    pub fn with_history(mut self, capacity: usize) -> Self
+    where
+        S: Debug,
+    {
+        self.history = Some(History::new(capacity));
+        self.describe_error = |kind| format!("{:?}", kind);
+        self
+    }
+
+    // Define a method to allow states to defer events, which requires cloning them into the queue
+    pub fn with_deferred_events(mut self) -> Self
+    where
+        E: Clone,
+    {
+        self.clone_event = Some(E::clone);
+        self
+    }
+
+    // Define a method to make every event and init transactional: if processing fails, the context
+    // and the current state are restored to their values from before the event
+    pub fn with_transactions(mut self) -> Self
+    where
+        CTX: Clone,
+    {
+        self.clone_context = Some(CTX::clone);
+        self
+    }
+
+    // Define a method to process the events posted to the context's outbox, at most `max_events` per call
+    pub fn with_event_queue(mut self, max_events: usize) -> Self
+    where
+        CTX: HasOutbox<E>,
+    {
+        self.drain_outbox = Some(|context: &mut CTX| context.outbox().take());
+        self.max_posted_events = max_events;
+        self
+    }
+
+    // Define a method to set how many transitions requested by on_enter are followed in a row (64 by default)
+    // before the machine gives up with ErrorKind::EnterChainTooLong
+    pub fn with_max_enter_chain(mut self, max_transitions: usize) -> Self {
+        self.max_enter_chain = max_transitions;
+        self
+    }
+
+    // Define a method to choose how long the state objects are cached
+    pub fn with_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.cache_policy = policy;
+        self
+    }
+
+    // Define a method to record the states, transitions and errors of this machine into `recorder`
+    pub fn with_coverage(mut self, recorder: CoverageRecorder<S>) -> Self {
+        self.coverage = Some(recorder);
+        self
+    }
+
+    // Define a method to register an observer, notified after the ones already registered
+    pub fn with_observer(mut self, observer: Box<dyn TransitionObserver<S, E> + Send>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    // Define a method to get the current state
+    pub fn get_current_state(&self) -> Option<&S> {
+        self.current_state.as_ref()
+    }
+
+    // Define a method to check whether the given state is the current state or one of its ancestors
+    pub fn is_in_state(&self, state: &S) -> bool {
+        match &self.current_state {
+            Some(current) => Self::ancestry(current).contains(state),
+            None => false,
+        }
+    }
+
+    // Define a method to check whether the machine has reached a final state
+    pub fn is_finished(&self) -> bool {
+        self.current_state.as_ref().is_some_and(F::is_final)
+    }
+
+    // Define a method to call on_context_changed on the current state and its ancestors, innermost first
+    fn context_changed(&mut self, context: &mut CTX) {
+        if let Some(current) = &self.current_state {
+            for state_id in Self::ancestry(current) {
+                F::on_context_changed(Self::state_mut(&mut self.states, &state_id), context);
+            }
+        }
+    }
+
+    // Define a method to get the deferred events waiting to be replayed, oldest first
+    pub fn get_deferred_events(&self) -> &[E] {
+        &self.deferred_events
+    }
+
+    // Define a method to get the recorded history, if enabled
+    pub fn history(&self) -> Option<&History<S>> {
+        self.history.as_ref()
+    }
+
+    // Define a method to get the attached transition table
+    pub fn get_transitions(&self) -> &TransitionTable<S, CTX, E> {
+        &self.transition_table
+    }
+
+    // Define a method to set the clock used by state timers. The pending timers keep the time they had left.
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let (old_now, new_now) = (self.clock.now(), clock.now());
+        for timer in self.timers.iter_mut() {
+            timer.deadline = new_now + timer.deadline.saturating_sub(old_now);
+        }
+        self.clock = clock;
+        self.clock_set = true;
+    }
+
+    // Define a method to check whether a clock was set, rather than the default SystemClock used
+    #[cfg(feature = "tokio")]
+    pub(crate) fn has_clock(&self) -> bool {
+        self.clock_set
+    }
+
+    // Define a method to register a callback run once the machine finishes, or right away if it already has
+    pub(crate) fn add_completion(&mut self, callback: CompletionCallback<S, CTX>, context: &CTX) {
+        self.completions.push(callback);
+        if self.is_finished() {
+            self.complete(context);
+        }
+    }
+
+    // Define a method to get the number of timers waiting to fire
+    pub(crate) fn timer_count(&self) -> usize {
+        self.timers.len()
+    }
+
+    // Define a method to get the deadline of the earliest pending timer, on the machine's clock
+    pub(crate) fn next_timer_deadline(&self) -> Option<Duration> {
+        self.timers.iter().map(|timer| timer.deadline).min()
+    }
+
+    // Define a method to get a future resolving when the earliest pending timer is due, or never
+    pub(crate) fn sleep_until_next_timer(&self) -> Sleep {
+        match self.next_timer_deadline() {
+            Some(deadline) => self.clock.sleep(deadline.saturating_sub(self.clock.now())),
+            None => Box::pin(std::future::pending()),
+        }
+    }
+
+    // Define a method to process the events of every due timer, earliest first.
+    // Returns how many timers fired.
+    pub(crate) async fn core_fire_due_timers(
+        &mut self,
+        context: &mut CTX,
+    ) -> Result<usize, Error<S, Err>> {
+        let mut fired = 0;
+        loop {
+            let now = self.clock.now();
+            let due = self
+                .timers
+                .iter()
+                .enumerate()
+                .filter(|(_, timer)| timer.deadline <= now)
+                .min_by_key(|(_, timer)| timer.deadline)
+                .map(|(index, _)| index);
+            match due {
+                Some(index) => {
+                    let timer = self.timers.remove(index);
+                    self.core_process_event(context, &timer.event).await?;
+                    fired += 1;
+                }
+                None => return Ok(fired),
+            }
+        }
+    }
+
+    // Define a method to initialize the state machine with an initial state.
+    // The state objects are created on first use and then kept or dropped as the cache policy says.
+    pub(crate) async fn core_init(
+        &mut self,
+        context: &mut CTX,
+        initial_state: S,
+    ) -> Result<(), Error<S, Err>> {
+        if self.current_state.is_none() {
+            let checkpoint = self.checkpoint(context);
+            if let Err(e) = self.enter(context, None, initial_state).await {
+                self.rollback(context, checkpoint);
+                return Err(e);
+            }
+            self.process_posted_events(context).await?;
+        }
+        Ok(())
+    }
+
+    // Define a method to exit the current state and its ancestors, innermost first, and forget the
+    // current state, the deferred events and the pending timers, so that the machine can be initialized again
+    pub(crate) async fn core_reset(&mut self, context: &mut CTX) {
+        self.exit_to(context, self.current_state.clone(), &[]).await;
+        self.current_state = None;
+        self.deferred_events.clear();
+        self.timers.clear();
+    }
+
+    // Define a method to process events and transition between states
+    pub(crate) async fn core_process_event(
+        &mut self,
+        context: &mut CTX,
+        event: &E,
+    ) -> Result<(), Error<S, Err>> {
+        let result = self.run_event(context, event).await;
+        if self.drain_outbox.is_none() {
+            return result;
+        }
+        let posted = self.process_posted_events(context).await;
+        result.and(posted)
+    }
+
+    // Define a method to run an event: defer it, or dispatch it and replay the deferred events after a transition
+    async fn run_event(&mut self, context: &mut CTX, event: &E) -> Result<(), Error<S, Err>> {
+        if self.defer_event(context, event)? {
+            return Ok(());
+        }
+        let from = self.current_state.clone();
+        let result = self.dispatch_event(context, event).await;
+        if result.is_ok() && self.current_state != from && !self.deferred_events.is_empty() {
+            return self.replay_deferred_events(context).await;
+        }
+        result
+    }
+
+    // Define a method to process a single event, recording it in the history and notifying the observers
+    async fn dispatch_event(&mut self, context: &mut CTX, event: &E) -> Result<(), Error<S, Err>> {
+        let from = self.current_state.clone();
+        if let Some(from) = &from {
+            self.notify(|observer| observer.before_dispatch(from, event));
+        }
+        let started = self.start_timing();
+        let checkpoint = self.checkpoint(context);
+        let fired = self
+            .handle_event(context, event)
+            .await
+            .map_err(|e| e.with_event(event));
+        if fired.is_err() {
+            self.rollback(context, checkpoint);
+        }
+        if let (Some(coverage), Some(from)) = (&self.coverage, &from) {
+            // Record the edge that fired, whatever state its target's on_enter then redirected to
+            match &fired {
+                Ok(target) => {
+                    coverage.record_transition(from, event, target.as_ref().unwrap_or(from))
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        ErrorKind::StateInvalid(_) | ErrorKind::InvalidEvent(_)
+                    ) =>
+                {
+                    coverage.record_error(from, event)
+                }
+                Err(_) => {}
+            }
+        }
+        let result = fired.map(|_| ());
+        if let (Some(history), Some(from), Some(to)) =
+            (&mut self.history, from.clone(), &self.current_state)
+        {
+            let outcome = match &result {
+                Ok(()) if from != *to => Outcome::Transition,
+                Ok(()) => Outcome::Handled,
+                Err(e) => Outcome::Error((self.describe_error)(e.kind())),
+            };
+            history.record(from.clone(), to.clone(), event, outcome);
+        }
+        if let (Ok(()), Some(from)) = (&result, &from) {
+            if self.current_state.as_ref() == Some(from) {
+                self.notify(|observer| observer.on_no_transition(from, event));
+            }
+        }
+        if let Some(from) = from {
+            let state = self.current_state.clone().unwrap_or(from);
+            let elapsed = Self::elapsed(started);
+            self.notify(|observer| observer.after_dispatch(&state, event, elapsed));
+        }
+        result
+    }
+
+    // Define a method to run an event through the global handler, the transition table and the states.
+    // Returns the target of the transition it fired, before any redirect by on_enter, if any.
+    async fn handle_event(
+        &mut self,
+        context: &mut CTX,
+        event: &E,
+    ) -> Result<Option<S>, Error<S, Err>> {
+        let c_state = match &self.current_state {
+            Some(state) => state.clone(),
+            None => return Err(Error::new(ErrorKind::StateMachineNotInitialized)),
+        };
+        if F::is_final(&c_state) {
+            return Err(Error::new(ErrorKind::Finished).with_from(Some(&c_state)));
+        }
+
+        if let Some(global_handler) = &mut self.global_event_handler {
+            match F::on_global_event(global_handler, event, context).await {
+                Response::Handled | Response::Unhandled => {}
+                Response::Error(s) => {
+                    let message = s.to_string();
+                    self.notify(|observer| observer.on_error(&c_state, &message));
+                    return Err(Error::new(ErrorKind::InvalidEvent(s)).with_from(Some(&c_state)));
+                }
+                Response::Transition(new_state) => {
+                    if new_state != c_state {
+                        self.transition_to(context, new_state.clone()).await?;
+                        return Ok(Some(new_state));
+                    }
+                }
+            }
+        }
+
+        if let Some(new_state) = self.select_transition(context, &c_state, event)? {
+            if new_state == c_state {
+                return Ok(None);
+            }
+            self.transition_to(context, new_state.clone()).await?;
+            return Ok(Some(new_state));
+        }
+
+        // Offer the event to the current state first, then to each of its ancestors in turn
+        let mut handler_state = c_state.clone();
+        loop {
+            let state = Self::state_mut(&mut self.states, &handler_state);
+            let started = Self::start_timing_for(&self.observers);
+            let response = F::on_event(state, event, context).await;
+            let elapsed = Self::elapsed(started);
+            self.notify(|observer| observer.after_event(&handler_state, event, elapsed));
+            match response {
+                Response::Handled => return Ok(None),
+                Response::Error(s) => {
+                    let message = s.to_string();
+                    self.notify(|observer| observer.on_error(&handler_state, &message));
+                    return Err(
+                        Error::new(ErrorKind::InvalidEvent(s)).with_from(Some(&handler_state))
+                    );
+                }
+                Response::Transition(new_state) => {
+                    if new_state == c_state {
+                        return Ok(None);
+                    }
+                    self.transition_to(context, new_state.clone()).await?;
+                    return Ok(Some(new_state));
+                }
+                Response::Unhandled => match F::parent(&handler_state) {
+                    Some(parent) => handler_state = parent,
+                    None => return Ok(None),
+                },
+            }
+        }
+    }
+
+    // Define a method to run the events posted to the outbox, in FIFO order, until none are left.
+    // A failing event does not stop the others; the first error is returned once the queue is empty.
+    async fn process_posted_events(&mut self, context: &mut CTX) -> Result<(), Error<S, Err>> {
+        let drain_outbox = match self.drain_outbox {
+            Some(drain_outbox) => drain_outbox,
+            None => return Ok(()),
+        };
+        let mut queue = VecDeque::new();
+        let mut processed = 0;
+        let mut result = Ok(());
+        loop {
+            queue.extend(drain_outbox(context));
+            let event = match queue.pop_front() {
+                Some(event) => event,
+                None => return result,
+            };
+            if processed == self.max_posted_events {
+                return Err(Error::new(ErrorKind::EventLimitExceeded(
+                    self.max_posted_events,
+                )));
+            }
+            processed += 1;
+            let event_result = self.run_event(context, &event).await;
+            if result.is_ok() {
+                result = event_result;
+            }
+        }
+    }
+
+    // Define a method to queue the event if the current state or one of its ancestors defers it.
+    // Returns whether the event was deferred.
+    fn defer_event(&mut self, context: &CTX, event: &E) -> Result<bool, Error<S, Err>> {
+        let c_state = match &self.current_state {
+            Some(state) => state.clone(),
+            None => return Ok(false),
+        };
+        if !Self::is_deferred(&mut self.states, context, &c_state, event) {
+            return Ok(false);
+        }
+        match self.clone_event {
+            Some(clone_event) => {
+                self.deferred_events.push(clone_event(event));
+                Ok(true)
+            }
+            None => Err(Error::new(ErrorKind::InternalError(
+                "deferring events requires StateMachine::with_deferred_events",
+            ))),
+        }
+    }
+
+    // Define a method to replay, in order, the deferred events the current state no longer defers.
+    // The queue is scanned again from the start every time a replayed event changes the state.
+    // A failing event does not stop the others; the first error is returned once the replay is done.
+    async fn replay_deferred_events(&mut self, context: &mut CTX) -> Result<(), Error<S, Err>> {
+        let mut index = 0;
+        let mut result = Ok(());
+        while index < self.deferred_events.len() {
+            let c_state = match &self.current_state {
+                Some(state) => state.clone(),
+                None => break,
+            };
+            if Self::is_deferred(
+                &mut self.states,
+                context,
+                &c_state,
+                &self.deferred_events[index],
+            ) {
+                index += 1;
+                continue;
+            }
+            let event = self.deferred_events.remove(index);
+            let event_result = self.dispatch_event(context, &event).await;
+            if result.is_ok() {
+                result = event_result;
+            }
+            if self.current_state.as_ref() != Some(&c_state) {
+                index = 0;
+            }
+        }
+        result
+    }
+
+    // Define a helper checking whether a state or one of its ancestors defers the event
+    fn is_deferred(
+        states: &mut HashMap<S, Box<F::State>>,
+        context: &CTX,
+        c_state: &S,
+        event: &E,
+    ) -> bool {
+        Self::ancestry(c_state)
+            .iter()
+            .any(|state_id| F::is_deferred(Self::state_mut(states, state_id), event, context))
+    }
+
+    // Define a method to find the transition table edge taken by the event from the current state or,
+    // failing that, from its closest ancestor with a matching edge. The action of the selected edge is run here.
+    fn select_transition(
+        &mut self,
+        context: &mut CTX,
+        c_state: &S,
+        event: &E,
+    ) -> Result<Option<S>, Error<S, Err>> {
+        for state_id in Self::ancestry(c_state) {
+            let mut rejected = false;
+            for transition in self.transition_table.find(&state_id, event) {
+                if transition.is_allowed(context, event) {
+                    transition.run_action(context, event);
+                    return Ok(Some(transition.get_to().clone()));
+                }
+                rejected = true;
+            }
+            if rejected {
+                return Err(Error::new(ErrorKind::GuardRejected).with_from(Some(&state_id)));
+            }
+        }
+        Ok(None)
+    }
+
+    // Define a method to handle state transitions
+    async fn transition_to(
+        &mut self,
+        context: &mut CTX,
+        new_state: S,
+    ) -> Result<(), Error<S, Err>> {
+        let target_path = Self::ancestry(&new_state);
+        let common_ancestor = self
+            .exit_to(context, self.current_state.clone(), &target_path)
+            .await;
+        self.enter(context, common_ancestor, new_state).await
+    }
+
+    // Define a method to enter every state between the active state `from` (exclusive) and `target`,
+    // outermost first, following any transition requested by on_enter
+    async fn enter(
+        &mut self,
+        context: &mut CTX,
+        from: Option<S>,
+        target: S,
+    ) -> Result<(), Error<S, Err>> {
+        let mut active = from;
+        let mut target = target;
+        // Every state entered in turn, starting with the original target
+        let mut chain = vec![target.clone()];
+        loop {
+            let entering: Vec<S> = Self::ancestry(&target)
+                .into_iter()
+                .take_while(|s| Some(s) != active.as_ref())
+                .collect();
+
+            let mut redirect = None;
+            for state_id in entering.into_iter().rev() {
+                self.inactive_states.retain(|s| *s != state_id);
+                let state = Self::state_mut(&mut self.states, &state_id);
+                let started = Self::start_timing_for(&self.observers);
+                let response = F::on_enter(state, context).await;
+                let elapsed = Self::elapsed(started);
+                // A state that stays entered schedules its timers right away
+                let mut timers = Timers::new();
+                match &response {
+                    Response::Error(_) => {}
+                    Response::Transition(s) if *s != state_id => {}
+                    _ => F::schedule_timers(state, context, &mut timers),
+                }
+                if let Response::Error(e) = response {
+                    // The state was not entered, so its object is handled as if it had been exited
+                    self.evict_state(&state_id);
+                    let message = e.to_string();
+                    self.notify(|observer| observer.on_error(&state_id, &message));
+                    return Err(Error::new(ErrorKind::StateInvalid(e))
+                        .with_from(self.current_state.as_ref())
+                        .with_to(&state_id));
+                }
+                self.notify(|observer| observer.after_enter(&state_id, elapsed));
+                if let Some(coverage) = &self.coverage {
+                    coverage.record_state(&state_id, F::parent(&state_id));
+                }
+                match response {
+                    Response::Transition(s) if s != state_id => {
+                        // The state is left without on_exit, so apply the cache policy to it right away
+                        self.evict_state(&state_id);
+                        redirect = Some(s);
+                        break;
+                    }
+                    _ => {
+                        self.start_timers(&state_id, timers);
+                        active = Some(state_id);
+                    }
+                }
+            }
+
+            match redirect {
+                None => break,
+                Some(next_state) => {
+                    chain.push(next_state.clone());
+                    if chain.len() > self.max_enter_chain + 1 {
+                        let message = format!(
+                            "on_enter transition chain longer than {}",
+                            self.max_enter_chain
+                        );
+                        self.notify(|observer| observer.on_error(&next_state, &message));
+                        let original = chain[0].clone();
+                        return Err(Error::new(ErrorKind::EnterChainTooLong(chain))
+                            .with_from(self.current_state.as_ref())
+                            .with_to(&original));
+                    }
+                    let next_path = Self::ancestry(&next_state);
+                    active = self.exit_to(context, active, &next_path).await;
+                    target = next_state;
+                }
+            }
+        }
+
+        self.current_state = active;
+        if self.is_finished() {
+            self.complete(context);
+        }
+
+        Ok(())
+    }
+
+    // Define a method to exit the active state and its ancestors, innermost first, up to the first one on `target_path`.
+    // Returns that common ancestor, if any.
+    async fn exit_to(
+        &mut self,
+        context: &mut CTX,
+        from: Option<S>,
+        target_path: &[S],
+    ) -> Option<S> {
+        let mut active = from;
+        while let Some(state_id) = active {
+            if target_path.contains(&state_id) {
+                return Some(state_id);
+            }
+            self.notify(|observer| observer.before_exit(&state_id));
+            let started = self.start_timing();
+            F::on_exit(Self::state_mut(&mut self.states, &state_id), context).await;
+            let elapsed = Self::elapsed(started);
+            self.evict_state(&state_id);
+            self.cancel_timers(&state_id);
+            self.notify(|observer| observer.after_exit(&state_id, elapsed));
+            active = F::parent(&state_id);
+        }
+        None
+    }
+
+    // Define a helper to start the timers requested by a state that was just entered
+    fn start_timers(&mut self, state_id: &S, timers: Timers<E>) {
+        let scheduled = timers.into_scheduled();
+        if scheduled.is_empty() {
+            return;
+        }
+        let now = self.clock.now();
+        for (delay, event) in scheduled {
+            self.timers.push(ScheduledTimer {
+                id: self.next_timer_id,
+                owner: state_id.clone(),
+                deadline: now + delay,
+                event,
+            });
+            self.next_timer_id += 1;
+        }
+    }
+
+    // Define a helper to cancel the timers owned by a state that was just exited, keeping them aside
+    // for a rollback if transactions are enabled
+    fn cancel_timers(&mut self, state_id: &S) {
+        if self.timers.is_empty() {
+            return;
+        }
+        let (cancelled, timers): (Vec<_>, Vec<_>) = std::mem::take(&mut self.timers)
+            .into_iter()
+            .partition(|timer| timer.owner == *state_id);
+        self.timers = timers;
+        if self.clone_context.is_some() {
+            self.cancelled_timers.extend(cancelled);
+        }
+    }
+
+    // Define a method to save what a failed transaction restores, if transactions are enabled
+    fn checkpoint(&mut self, context: &CTX) -> Option<Checkpoint<S, CTX>> {
+        self.cancelled_timers.clear();
+        self.evicted_states.clear();
+        self.clone_context.map(|clone_context| Checkpoint {
+            context: clone_context(context),
+            current_state: self.current_state.clone(),
+            next_timer_id: self.next_timer_id,
+        })
+    }
+
+    // Define a method to restore the context, the current state and the timers saved by `checkpoint`.
+    // Whatever the states' on_exit and on_enter did to the state objects themselves is kept.
+    fn rollback(&mut self, context: &mut CTX, checkpoint: Option<Checkpoint<S, CTX>>) {
+        if let Some(checkpoint) = checkpoint {
+            *context = checkpoint.context;
+            self.current_state = checkpoint.current_state;
+            // Bring back the objects the cache policy dropped for the states that are active again,
+            // keeping the oldest one when a state was evicted more than once
+            let active = self
+                .current_state
+                .as_ref()
+                .map(Self::ancestry)
+                .unwrap_or_default();
+            for (state_id, state) in std::mem::take(&mut self.evicted_states).into_iter().rev() {
+                if active.contains(&state_id) {
+                    self.inactive_states.retain(|s| *s != state_id);
+                    self.states.insert(state_id, state);
+                }
+            }
+            // Drop the timers scheduled since the checkpoint and bring back the ones cancelled by the exits
+            let first_new = checkpoint.next_timer_id;
+            self.timers.retain(|timer| timer.id < first_new);
+            self.timers.extend(
+                self.cancelled_timers
+                    .drain(..)
+                    .filter(|timer| timer.id < first_new),
+            );
+            self.timers.sort_by_key(|timer| timer.id);
+        }
+    }
+
+    // Define a helper to resolve the pending completion futures with the current state and context
+    fn complete(&mut self, context: &CTX) {
+        if let Some(state) = &self.current_state {
+            for complete in self.completions.drain(..) {
+                complete(state, context);
+            }
+        }
+    }
+
+    // Define a helper applying the cache policy to the object of a state that was just exited
+    fn evict_state(&mut self, state_id: &S) {
+        match self.cache_policy {
+            CachePolicy::Forever => {}
+            CachePolicy::RecreateOnEntry => {
+                self.drop_state(state_id.clone());
+            }
+            CachePolicy::Lru(capacity) => {
+                self.inactive_states.push_back(state_id.clone());
+                while self.states.len() > capacity {
+                    match self.inactive_states.pop_front() {
+                        Some(oldest) => self.drop_state(oldest),
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    // Define a helper dropping a state object, keeping it aside for a rollback if transactions are enabled
+    fn drop_state(&mut self, state_id: S) {
+        if let Some(state) = self.states.remove(&state_id) {
+            if self.clone_context.is_some() {
+                self.evicted_states.push((state_id, state));
+            }
+        }
+    }
+
+    // Define a helper reading the time before a callback, only if observers are told how long it took
+    fn start_timing(&self) -> Option<Instant> {
+        Self::start_timing_for(&self.observers)
+    }
+
+    fn start_timing_for(observers: &[Box<dyn TransitionObserver<S, E> + Send>]) -> Option<Instant> {
+        if observers.is_empty() {
+            None
+        } else {
+            Some(Instant::now())
+        }
+    }
+
+    // Define a helper measuring the time elapsed since `start_timing`, zero if nothing was timed
+    fn elapsed(started: Option<Instant>) -> Duration {
+        started.map(|started| started.elapsed()).unwrap_or_default()
+    }
+
+    // Define a helper to call every registered observer in turn
+    fn notify(&mut self, mut f: impl FnMut(&mut (dyn TransitionObserver<S, E> + Send))) {
+        for observer in self.observers.iter_mut() {
+            f(observer.as_mut());
+        }
+    }
+
+    // Define a helper returning a state followed by all of its ancestors, innermost first
+    fn ancestry(state: &S) -> Vec<S> {
+        let mut path = vec![state.clone()];
+        while let Some(parent) = F::parent(path.last().unwrap()) {
+            path.push(parent);
+        }
+        path
+    }
+
+    // Define a helper to get a cached state object, creating it the first time the state is used
+    fn state_mut<'a>(states: &'a mut HashMap<S, Box<F::State>>, state: &S) -> &'a mut F::State {
+        states
+            .entry(state.clone())
+            .or_insert_with(|| F::create(state))
+    }
+}
+
+// Implement the graph exports for regions whose states can be printed
+impl<S, CTX, E, Err, F> Region<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug,
+    E: Debug,
+    F: FrontEnd<S, CTX, E, Err>,
+{
+    // Define a method to export the transition table as Graphviz DOT, highlighting the current state
+    pub fn to_dot(&self) -> String {
+        graph::to_dot(&self.transition_table, self.current_state.as_ref())
+    }
+
+    // Define a method to export the transition table as a Mermaid state diagram, highlighting the current state
+    pub fn to_mermaid(&self) -> String {
+        graph::to_mermaid(&self.transition_table, self.current_state.as_ref())
+    }
+}
+
+// Implement methods for the StateMachine struct, running its region against the context it owns
+impl<S, CTX, E, Err, F> StateMachine<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone,
+    E: Debug,
+    Err: Debug + Display,
+    F: FrontEnd<S, CTX, E, Err>,
+{
+    // Define a constructor used by the front-ends' `new`
+    pub(crate) fn create(context: CTX, handler: Option<Box<F::Handler>>) -> Self {
+        Self {
+            region: Region::create(handler),
+            context,
+        }
+    }
+
+    // Define a helper applying one of the region's builder methods
+    fn map_region(
+        self,
+        f: impl FnOnce(Region<S, CTX, E, Err, F>) -> Region<S, CTX, E, Err, F>,
+    ) -> Self {
+        Self {
+            region: f(self.region),
+            context: self.context,
+        }
+    }
+
+    // Define a method to attach a transition table, consulted before the states' own on_event
+    pub fn with_transitions(self, table: TransitionTable<S, CTX, E>) -> Self {
+        self.map_region(|region| region.with_transitions(table))
+    }
+
+    // Define a method to record the last `capacity` processed events in a history ring buffer
+    pub fn with_history(self, capacity: usize) -> Self
+    where
+        S: Debug,
+    {
+        self.map_region(|region| region.with_history(capacity))
+    }
+
+    // Define a method to allow states to defer events, which requires cloning them into the queue
+    pub fn with_deferred_events(self) -> Self
+    where
+        E: Clone,
+    {
+        self.map_region(|region| region.with_deferred_events())
+    }
+
+    // Define a method to make every event and init transactional: if processing fails, the context
+    // and the current state are restored to their values from before the event
+    pub fn with_transactions(self) -> Self
+    where
+        CTX: Clone,
+    {
+        self.map_region(|region| region.with_transactions())
+    }
+
+    // Define a method to process the events posted to the context's outbox, at most `max_events` per call
+    pub fn with_event_queue(self, max_events: usize) -> Self
+    where
+        CTX: HasOutbox<E>,
+    {
+        self.map_region(|region| region.with_event_queue(max_events))
+    }
+
+    // Define a method to set how many transitions requested by on_enter are followed in a row (64 by default)
+    // before the machine gives up with ErrorKind::EnterChainTooLong
+    pub fn with_max_enter_chain(self, max_transitions: usize) -> Self {
+        self.map_region(|region| region.with_max_enter_chain(max_transitions))
+    }
+
+    // Define a method to choose how long the state objects are cached
+    pub fn with_cache_policy(self, policy: CachePolicy) -> Self {
+        self.map_region(|region| region.with_cache_policy(policy))
+    }
+
+    // Define a method to record the states, transitions and errors of this machine into `recorder`
+    pub fn with_coverage(self, recorder: CoverageRecorder<S>) -> Self {
+        self.map_region(|region| region.with_coverage(recorder))
+    }
+
+    // Define a method to register an observer, notified after the ones already registered
+    pub fn with_observer(self, observer: Box<dyn TransitionObserver<S, E> + Send>) -> Self {
+        self.map_region(|region| region.with_observer(observer))
+    }
+
+    // Define a method to get the current state
+    pub fn get_current_state(&self) -> Option<&S> {
+        self.region.get_current_state()
+    }
+
+    // Define a method to check whether the given state is the current state or one of its ancestors
+    pub fn is_in_state(&self, state: &S) -> bool {
+        self.region.is_in_state(state)
+    }
+
+    // Define a method to check whether the machine has reached a final state
+    pub fn is_finished(&self) -> bool {
+        self.region.is_finished()
+    }
+
+    // Define a method to get a reference to the context
+    pub fn get_context(&self) -> &CTX {
+        &self.context
+    }
+
+    // Define a method to get a mutable reference to the context. The states are not told about
+    // the changes; use `with_context` for that.
+    pub fn get_context_mut(&mut self) -> &mut CTX {
+        &mut self.context
+    }
+
+    // Define a method to change the context from outside the machine, then call on_context_changed
+    // on the current state and its ancestors, innermost first
+    pub fn with_context<R>(&mut self, f: impl FnOnce(&mut CTX) -> R) -> R {
+        let result = f(&mut self.context);
+        self.region.context_changed(&mut self.context);
+        result
+    }
+
+    // Define a method to take the machine apart once it is done, returning the context and the current state
+    pub fn into_parts(self) -> (CTX, Option<S>) {
+        (self.context, self.region.current_state)
+    }
+
+    // Define a method to get the deferred events waiting to be replayed, oldest first
+    pub fn get_deferred_events(&self) -> &[E] {
+        self.region.get_deferred_events()
+    }
+
+    // Define a method to get the recorded history, if enabled
+    pub fn history(&self) -> Option<&History<S>> {
+        self.region.history()
+    }
+
+    // Define a method to get the attached transition table
+    pub fn get_transitions(&self) -> &TransitionTable<S, CTX, E> {
+        self.region.get_transitions()
+    }
+
+    // Define a method to set the clock used by state timers. The pending timers keep the time they had left.
+    pub(crate) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.region.set_clock(clock)
+    }
+
+    // Define a method to check whether a clock was set, rather than the default SystemClock used
+    #[cfg(feature = "tokio")]
+    pub(crate) fn has_clock(&self) -> bool {
+        self.region.has_clock()
+    }
+
+    // Define a method to register a callback run once the machine finishes, or right away if it already has
+    pub(crate) fn add_completion(&mut self, callback: CompletionCallback<S, CTX>) {
+        self.region.add_completion(callback, &self.context)
+    }
+
+    // Define a method to get the number of timers waiting to fire
+    pub(crate) fn timer_count(&self) -> usize {
+        self.region.timer_count()
+    }
+
+    // Define a method to get the deadline of the earliest pending timer, on the machine's clock
+    #[cfg(feature = "tokio")]
+    pub(crate) fn next_timer_deadline(&self) -> Option<Duration> {
+        self.region.next_timer_deadline()
+    }
+
+    // Define a method to get a future resolving when the earliest pending timer is due, or never
+    pub(crate) fn sleep_until_next_timer(&self) -> Sleep {
+        self.region.sleep_until_next_timer()
+    }
+
+    // Define a method to process the events of every due timer, earliest first.
+    // Returns how many timers fired.
+    pub(crate) async fn core_fire_due_timers(&mut self) -> Result<usize, Error<S, Err>> {
+        self.region.core_fire_due_timers(&mut self.context).await
+    }
+
+    // Define a method to initialize the state machine with an initial state
+    pub(crate) async fn core_init(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+        self.region
+            .core_init(&mut self.context, initial_state)
+            .await
+    }
+
+    // Define a method to exit the current state and its ancestors and forget the current state,
+    // so that the machine can be initialized again
+    pub(crate) async fn core_reset(&mut self) {
+        self.region.core_reset(&mut self.context).await
+    }
+
+    // Define a method to process events and transition between states
+    pub(crate) async fn core_process_event(&mut self, event: &E) -> Result<(), Error<S, Err>> {
+        self.region
+            .core_process_event(&mut self.context, event)
+            .await
+    }
+}
+
+// Implement the graph exports for machines whose states can be printed
+impl<S, CTX, E, Err, F> StateMachine<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug,
+    E: Debug,
+    F: FrontEnd<S, CTX, E, Err>,
+{
+    // Define a method to export the transition table as Graphviz DOT, highlighting the current state
+    pub fn to_dot(&self) -> String {
+        self.region.to_dot()
+    }
+
+    // Define a method to export the transition table as a Mermaid state diagram, highlighting the current state
+    pub fn to_mermaid(&self) -> String {
+        self.region.to_mermaid()
+    }
+}
+
+// Implement snapshot and restore for machines whose context can be cloned
+#[cfg(feature = "serde")]
+impl<S, CTX, E, Err, F> StateMachine<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone,
+    CTX: Clone,
+    E: Debug,
+    Err: Debug + Display,
+    F: FrontEnd<S, CTX, E, Err>,
+{
+    // Define a method to capture the current state, the context and the per-state data
+    pub fn snapshot(&self) -> Result<Snapshot<S, CTX>, Error<S, Err>> {
+        let current_state = match &self.region.current_state {
+            Some(state) => state.clone(),
+            None => return Err(Error::new(ErrorKind::StateMachineNotInitialized)),
+        };
+        let state_data = self
+            .region
+            .states
+            .iter()
+            .filter_map(|(id, state)| F::save_data(state).map(|data| (id.clone(), data)))
+            .collect();
+        Ok(Snapshot {
+            current_state,
+            context: self.context.clone(),
+            state_data,
+        })
+    }
+
+    // Define a constructor used by the front-ends' `restore`; unlike init, no on_enter is called
+    pub(crate) fn create_from_snapshot(
+        snapshot: Snapshot<S, CTX>,
+        handler: Option<Box<F::Handler>>,
+    ) -> Self {
+        let mut machine = Self::create(snapshot.context, handler);
+        for (id, data) in snapshot.state_data {
+            F::restore_data(
+                Region::<S, CTX, E, Err, F>::state_mut(&mut machine.region.states, &id),
+                &data,
+            );
+        }
+        machine.region.current_state = Some(snapshot.current_state);
+        machine
+    }
+}
+
+// Implement the AnyRegion trait by running the region against the shared context, with its states
+// reported through their Debug representation
+impl<S, CTX, E, Err, F> AnyRegion<CTX, E, Err> for InitialRegion<S, CTX, E, Err, F>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug + Send + 'static,
+    CTX: 'static,
+    E: Debug + 'static,
+    Err: Debug + Display + Send + 'static,
+    F: ParallelFrontEnd<S, CTX, E, Err> + 'static,
+    Region<S, CTX, E, Err, F>: Send,
+{
+    fn init<'a>(&'a mut self, context: &'a mut CTX) -> RegionFuture<'a, String, Err> {
+        let future = F::init_region(&mut self.region, context, self.initial_state.clone());
+        Box::pin(async move {
+            future
+                .await
+                .map_err(|e| e.map_states(|s| format!("{:?}", s)))
+        })
+    }
+
+    fn process_event<'a>(
+        &'a mut self,
+        event: &'a E,
+        context: &'a mut CTX,
+    ) -> RegionFuture<'a, String, Err> {
+        let future = F::process_region_event(&mut self.region, context, event);
+        Box::pin(async move {
+            future
+                .await
+                .map_err(|e| e.map_states(|s| format!("{:?}", s)))
+        })
+    }
+
+    fn is_finished(&self) -> bool {
+        self.region.is_finished()
+    }
+
+    fn state_name(&self) -> Option<String> {
+        self.region
+            .get_current_state()
+            .map(|state| format!("{:?}", state))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        &self.region
+    }
+}
+
+// Implement methods for the ParallelStateMachine struct
+impl<CTX, E, Err, F> ParallelStateMachine<CTX, E, Err, F>
+where
+    CTX: Send + 'static,
+    E: Debug + 'static,
+    Err: Debug + Display + Send + 'static,
+    F: 'static,
+{
+    // Define a constructor for the ParallelStateMachine struct, owning the context shared by the regions
+    pub fn new(context: CTX) -> Self {
+        Self {
+            regions: Vec::new(),
+            context,
+            _front_end: PhantomData,
+        }
+    }
+
+    // Define a method to add a region starting in `initial_state`
+    pub fn with_region<S>(mut self, region: Region<S, CTX, E, Err, F>, initial_state: S) -> Self
+    where
+        S: Hash + PartialEq + Eq + Clone + Debug + Send + 'static,
+        F: ParallelFrontEnd<S, CTX, E, Err>,
+        Region<S, CTX, E, Err, F>: Send,
+    {
+        self.regions.push(Box::new(InitialRegion {
+            region,
+            initial_state,
+        }));
+        self
+    }
+
+    // Define a method to check whether every region has reached a final state
+    pub fn is_finished(&self) -> bool {
+        self.regions.iter().all(|region| region.is_finished())
+    }
+
+    // Define a method to get the current state of every region, in the order they were added, as the Debug
+    // representation of the state; None for a region that is not initialized
+    pub fn get_states(&self) -> Vec<Option<String>> {
+        self.regions
+            .iter()
+            .map(|region| region.state_name())
+            .collect()
+    }
+
+    // Define a method to get the current state of the region at `index`, in the order the regions were
+    // added, if its states are of type `S`
+    pub fn get_region_state<S>(&self, index: usize) -> Option<&S>
+    where
+        S: Hash + PartialEq + Eq + Clone + 'static,
+        F: FrontEnd<S, CTX, E, Err>,
+    {
+        self.regions
+            .get(index)
+            .and_then(|region| region.as_any().downcast_ref::<Region<S, CTX, E, Err, F>>())
+            .and_then(|region| region.get_current_state())
+    }
+
+    // Define a method to get the current state of the first region whose states are of type `S`
+    pub fn get_state<S>(&self) -> Option<&S>
+    where
+        S: Hash + PartialEq + Eq + Clone + 'static,
+        F: FrontEnd<S, CTX, E, Err>,
+    {
+        self.get_region::<S>()
+            .and_then(|region| region.get_current_state())
+    }
+
+    // Define a method to get the first region whose states are of type `S`
+    pub fn get_region<S>(&self) -> Option<&Region<S, CTX, E, Err, F>>
+    where
+        S: Hash + PartialEq + Eq + Clone + 'static,
+        F: FrontEnd<S, CTX, E, Err>,
+    {
+        self.regions
+            .iter()
+            .find_map(|region| region.as_any().downcast_ref())
+    }
+
+    // Define a method to get a reference to the shared context
+    pub fn get_context(&self) -> &CTX {
+        &self.context
+    }
+
+    // Define a method to initialize every region, stopping at the first one that fails
+    pub(crate) async fn core_init(&mut self) -> Result<(), Error<String, Err>> {
+        for region in self.regions.iter_mut() {
+            region.init(&mut self.context).await?;
+        }
+        Ok(())
+    }
+
+    // Define a method to process an event in every region. All regions see the event even if one fails;
+    // the first error is returned.
+    pub(crate) async fn core_process_event(&mut self, event: &E) -> Result<(), Error<String, Err>> {
+        let mut result = Ok(());
+        for region in self.regions.iter_mut() {
+            let region_result = region.process_event(event, &mut self.context).await;
+            if result.is_ok() {
+                result = region_result;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,211 @@
+//! The error type shared by the `sync` and `Async` state machines.
+//!
+//! An `Error` pairs an `ErrorKind` with the context it was raised in: the state the machine was in,
+//! the state being entered, and the `Debug` representation of the event being processed. `Err` is the
+//! type carried by `Response::Error`.
+//!
+//! The errors only need `core`: the text of the errors raised by the machines is a `&'static str`,
+//! and so is `Err` by default. The `sync` and `Async` modules name them with `String` as the default
+//! `Err`. Without `std`, the event is not recorded and there is no `ErrorKind::EnterChainTooLong`,
+//! which lists the states of the chain; `ErrorKind` is non-exhaustive, so enabling `std` breaks no
+//! match on it.
+
+use core::fmt::{self, Debug, Display};
+
+// Define the ErrorKind enum, which tells what went wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind<S, Err = &'static str> {
+    StateNotFound(&'static str),
+    // An on_enter callback returned Response::Error
+    StateInvalid(Err),
+    // The global handler or an on_event callback returned Response::Error
+    InvalidEvent(Err),
+    StateMachineNotInitialized,
+    InternalError(&'static str),
+    // Every transition table edge matching the event was rejected by its guard
+    GuardRejected,
+    // More posted events than the configured limit were processed for a single call
+    EventLimitExceeded(usize),
+    // on_enter kept requesting transitions beyond the configured limit; holds every state entered in turn,
+    // then the one it would have entered next
+    #[cfg(feature = "std")]
+    EnterChainTooLong(Vec<S>),
+    // Same as EnterChainTooLong for the machines that only need `core`; holds the state it would have
+    // entered next and the number of states in the chain, that one included
+    EnterChainLimitExceeded {
+        last: S,
+        length: usize,
+    },
+    // The machine is in a final state and no longer processes events
+    Finished,
+}
+
+impl<S: Debug, Err: Display> Display for ErrorKind<S, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::StateNotFound(state) => write!(f, "state not found: {}", state),
+            ErrorKind::StateInvalid(e) => write!(f, "state cannot be entered: {}", e),
+            ErrorKind::InvalidEvent(e) => write!(f, "invalid event: {}", e),
+            ErrorKind::StateMachineNotInitialized => write!(f, "state machine is not initialized"),
+            ErrorKind::InternalError(e) => write!(f, "internal error: {}", e),
+            ErrorKind::GuardRejected => write!(f, "every guard rejected the event"),
+            ErrorKind::EventLimitExceeded(limit) => {
+                write!(f, "more than {} posted events in a single call", limit)
+            }
+            #[cfg(feature = "std")]
+            ErrorKind::EnterChainTooLong(chain) => {
+                write!(f, "on_enter transition chain too long: {:?}", chain)
+            }
+            ErrorKind::EnterChainLimitExceeded { last, length } => write!(
+                f,
+                "on_enter transition chain of {} states too long, reaching {:?}",
+                length, last
+            ),
+            ErrorKind::Finished => write!(f, "state machine is finished"),
+        }
+    }
+}
+
+// Define the Error struct, which is an ErrorKind with the states and event involved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error<S, Err = &'static str> {
+    kind: ErrorKind<S, Err>,
+    from: Option<S>,
+    to: Option<S>,
+    #[cfg(feature = "std")]
+    event: Option<String>,
+}
+
+impl<S, Err> Error<S, Err> {
+    // Define a constructor for an error without any context
+    pub fn new(kind: ErrorKind<S, Err>) -> Self {
+        Self {
+            kind,
+            from: None,
+            to: None,
+            #[cfg(feature = "std")]
+            event: None,
+        }
+    }
+
+    // Define a method to get what went wrong
+    pub fn kind(&self) -> &ErrorKind<S, Err> {
+        &self.kind
+    }
+
+    // Define a method to take the kind out of the error, typically to match on it
+    pub fn into_kind(self) -> ErrorKind<S, Err> {
+        self.kind
+    }
+
+    // Define a method to get the state the machine was in when the error occurred
+    pub fn get_from(&self) -> Option<&S> {
+        self.from.as_ref()
+    }
+
+    // Define a method to get the state that was being entered, if any
+    pub fn get_to(&self) -> Option<&S> {
+        self.to.as_ref()
+    }
+
+    // Define a method to get the Debug representation of the event being processed, if any
+    #[cfg(feature = "std")]
+    pub fn get_event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    // Define a method to set the source state, unless an inner call already did
+    pub(crate) fn with_from(mut self, from: Option<&S>) -> Self
+    where
+        S: Clone,
+    {
+        if self.from.is_none() {
+            self.from = from.cloned();
+        }
+        self
+    }
+
+    // Define a method to set the target state, unless an inner call already did
+    pub(crate) fn with_to(mut self, to: &S) -> Self
+    where
+        S: Clone,
+    {
+        if self.to.is_none() {
+            self.to = Some(to.clone());
+        }
+        self
+    }
+
+    // Define a method to set the event, unless an inner call already did
+    #[cfg(feature = "std")]
+    pub(crate) fn with_event(mut self, event: &impl Debug) -> Self {
+        if self.event.is_none() {
+            self.event = Some(format!("{:?}", event));
+        }
+        self
+    }
+
+    // Define a method to convert the states recorded in the error, keeping the rest as is
+    #[cfg(feature = "std")]
+    pub(crate) fn map_states<T>(self, f: impl Fn(S) -> T) -> Error<T, Err> {
+        let kind = match self.kind {
+            ErrorKind::StateNotFound(state) => ErrorKind::StateNotFound(state),
+            ErrorKind::StateInvalid(e) => ErrorKind::StateInvalid(e),
+            ErrorKind::InvalidEvent(e) => ErrorKind::InvalidEvent(e),
+            ErrorKind::StateMachineNotInitialized => ErrorKind::StateMachineNotInitialized,
+            ErrorKind::InternalError(e) => ErrorKind::InternalError(e),
+            ErrorKind::GuardRejected => ErrorKind::GuardRejected,
+            ErrorKind::EventLimitExceeded(limit) => ErrorKind::EventLimitExceeded(limit),
+            ErrorKind::EnterChainTooLong(chain) => {
+                ErrorKind::EnterChainTooLong(chain.into_iter().map(&f).collect())
+            }
+            ErrorKind::EnterChainLimitExceeded { last, length } => {
+                ErrorKind::EnterChainLimitExceeded {
+                    last: f(last),
+                    length,
+                }
+            }
+            ErrorKind::Finished => ErrorKind::Finished,
+        };
+        Error {
+            kind,
+            from: self.from.map(&f),
+            to: self.to.map(&f),
+            event: self.event,
+        }
+    }
+}
+
+impl<S, Err> From<ErrorKind<S, Err>> for Error<S, Err> {
+    fn from(kind: ErrorKind<S, Err>) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl<S: Debug, Err: Display> Display for Error<S, Err> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        let mut separator = " (";
+        #[cfg(feature = "std")]
+        if let Some(event) = &self.event {
+            write!(f, "{}event {}", separator, event)?;
+            separator = ", ";
+        }
+        if let Some(from) = &self.from {
+            write!(f, "{}in state {:?}", separator, from)?;
+            separator = ", ";
+        }
+        if let Some(to) = &self.to {
+            write!(f, "{}entering {:?}", separator, to)?;
+            separator = ", ";
+        }
+        if separator == ", " {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Debug, Err: Debug + Display> std::error::Error for Error<S, Err> {}
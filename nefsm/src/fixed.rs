@@ -0,0 +1,187 @@
+//! A state machine with fixed-capacity storage, for targets without `std` or an allocator.
+//!
+//! `StateMachine` is the counterpart of `sync::StateMachine` that only needs `core`. Its states work the
+//! same way: `FsmEnum::create` builds the object of a state the first time it is entered, the object
+//! is kept for the next entries, and its `Stateful` callbacks handle the events. Instead of a `HashMap`
+//! of boxed objects, the objects are stored in an array of `N` slots indexed by `FsmEnum::index`,
+//! typically the discriminant of the state. Since they are not boxed, the objects of all the states
+//! are one type, `FsmEnum::State`, usually an enum with a variant per state.
+//!
+//! States are flat, and there is no global handler, transition table or other `sync::StateMachine`
+//! feature that needs an allocator. Like `StaticStateMachine`, it is available with or without `std`.
+
+use core::marker::PhantomData;
+
+use crate::error::{Error, ErrorKind};
+use crate::response::Response;
+
+// Define the FsmEnum trait, which is used to create new state objects and find their slot
+pub trait FsmEnum<CTX, E, Err = &'static str>: Sized {
+    // The object of a state
+    type State: Stateful<Self, CTX, E, Err>;
+
+    fn create(&self) -> Self::State;
+
+    // Define the slot of the state's object, below the capacity of the machine
+    fn index(&self) -> usize;
+
+    // Define whether a state is final. Once the machine is in a final state it is finished and
+    // rejects any further event.
+    fn is_final(&self) -> bool {
+        false
+    }
+}
+
+// Define the Stateful trait, which contains the event handling methods for each state
+pub trait Stateful<S, CTX, E, Err = &'static str> {
+    fn on_enter(&mut self, context: &mut CTX) -> Response<S, Err>;
+    fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S, Err>;
+    fn on_exit(&mut self, context: &mut CTX);
+}
+
+// Define the StateMachine struct, which keeps up to `N` state objects
+pub struct StateMachine<S: FsmEnum<CTX, E, Err>, CTX, E, Err, const N: usize> {
+    states: [Option<S::State>; N],
+    current_state: Option<S>,
+    context: CTX,
+    max_enter_chain: usize,
+    _event: PhantomData<fn(&E) -> Err>,
+}
+
+// Implement methods for the StateMachine struct
+impl<S, CTX, E, Err, const N: usize> StateMachine<S, CTX, E, Err, N>
+where
+    S: FsmEnum<CTX, E, Err> + PartialEq + Clone,
+{
+    // Define a constructor for the StateMachine struct
+    pub fn new(context: CTX) -> Self {
+        Self {
+            states: core::array::from_fn(|_| None),
+            current_state: None,
+            context,
+            max_enter_chain: 64,
+            _event: PhantomData,
+        }
+    }
+
+    // Define a method to set how many transitions requested by on_enter are followed in a row (64 by default)
+    // before the machine gives up with ErrorKind::EnterChainLimitExceeded
+    pub fn with_max_enter_chain(mut self, max_transitions: usize) -> Self {
+        self.max_enter_chain = max_transitions;
+        self
+    }
+
+    // Define a method to get the current state
+    pub fn get_current_state(&self) -> Option<&S> {
+        self.current_state.as_ref()
+    }
+
+    // Define a method to check whether the machine has reached a final state
+    pub fn is_finished(&self) -> bool {
+        self.current_state.as_ref().is_some_and(S::is_final)
+    }
+
+    // Define a method to get a reference to the context
+    pub fn get_context(&self) -> &CTX {
+        &self.context
+    }
+
+    // Define a method to get a mutable reference to the context
+    pub fn get_context_mut(&mut self) -> &mut CTX {
+        &mut self.context
+    }
+
+    // Define a method to take the machine apart once it is done, returning the context and the current state
+    pub fn into_parts(self) -> (CTX, Option<S>) {
+        (self.context, self.current_state)
+    }
+
+    // Define a method to initialize the state machine with an initial state
+    pub fn init(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+        if self.current_state.is_none() {
+            self.enter(initial_state)?;
+        }
+        Ok(())
+    }
+
+    // Define a method to exit the current state and forget it, so that the machine can be initialized again
+    pub fn reset(&mut self) {
+        if let Some(state_id) = self.current_state.take() {
+            if let Ok(state) = Self::state_mut(&mut self.states, &state_id) {
+                state.on_exit(&mut self.context);
+            }
+        }
+    }
+
+    // Define a method to reset the machine and initialize it again in `initial_state`
+    pub fn reinit(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+        self.reset();
+        self.init(initial_state)
+    }
+
+    // Define a method to process events and transition between states
+    pub fn process_event(&mut self, event: &E) -> Result<(), Error<S, Err>> {
+        let c_state = match &self.current_state {
+            Some(state) => state.clone(),
+            None => return Err(Error::new(ErrorKind::StateMachineNotInitialized)),
+        };
+        if c_state.is_final() {
+            return Err(Error::new(ErrorKind::Finished).with_from(Some(&c_state)));
+        }
+        let state = Self::state_mut(&mut self.states, &c_state)?;
+        match state.on_event(event, &mut self.context) {
+            Response::Handled | Response::Unhandled => Ok(()),
+            Response::Error(e) => {
+                Err(Error::new(ErrorKind::InvalidEvent(e)).with_from(Some(&c_state)))
+            }
+            Response::Transition(new_state) => {
+                if new_state != c_state {
+                    state.on_exit(&mut self.context);
+                    self.enter(new_state)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Define a method to enter a state, following any transition requested by on_enter. If on_enter
+    // fails, the previous state stays current, as in `sync::StateMachine`.
+    fn enter(&mut self, target: S) -> Result<(), Error<S, Err>> {
+        let mut state_id = target.clone();
+        for _ in 0..=self.max_enter_chain {
+            let state = Self::state_mut(&mut self.states, &state_id)?;
+            match state.on_enter(&mut self.context) {
+                Response::Error(e) => {
+                    return Err(Error::new(ErrorKind::StateInvalid(e))
+                        .with_from(self.current_state.as_ref())
+                        .with_to(&state_id));
+                }
+                Response::Transition(next_state) if next_state != state_id => state_id = next_state,
+                _ => {
+                    self.current_state = Some(state_id);
+                    return Ok(());
+                }
+            }
+        }
+        Err(Error::new(ErrorKind::EnterChainLimitExceeded {
+            last: state_id,
+            length: self.max_enter_chain + 2,
+        })
+        .with_from(self.current_state.as_ref())
+        .with_to(&target))
+    }
+
+    // Define a helper to get the object of a state, creating it the first time the state is used
+    fn state_mut<'a>(
+        states: &'a mut [Option<S::State>; N],
+        state: &S,
+    ) -> Result<&'a mut S::State, Error<S, Err>> {
+        match states.get_mut(state.index()) {
+            Some(slot) => Ok(slot.get_or_insert_with(|| state.create())),
+            None => Err(Error::new(ErrorKind::StateNotFound(
+                "the state's index is beyond the capacity of the machine",
+            ))
+            .with_to(state)),
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Property-based testing of `sync` state machines, behind the `testing` feature.
+//!
+//! A `Fuzzer` builds a fresh machine for every run, feeds it a random sequence of events from a
+//! generator, and checks a list of invariants over the current state and the context after `init` and
+//! after every event. A machine that `init` leaves without a current state fails the built-in invariant
+//! "the machine is initialized". When an invariant fails, the sequence is shrunk by removing events for
+//! as long as the remaining ones still make an invariant fail, so the `FuzzReport` holds a minimal
+//! reproduction.
+//! A failure that does not happen again when the sequence is replayed, for instance because the context
+//! or an invariant depends on time or randomness, is reported unshrunk and flagged as not reproducible.
+//! The report also lists the states and the transitions the runs covered. Runs are driven by a seeded
+//! `Rng`, so the same seed always replays the same sequences.
+
+use std::fmt::{self, Debug, Display};
+use std::hash::Hash;
+
+use crate::sync::{FsmEnum, StateMachine};
+use crate::table::event_name;
+
+// Define the Rng struct, a small SplitMix64 generator handed to event generators
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // Define a constructor for a generator producing the sequence of `seed`
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // Define a method to draw the next 64 random bits
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Define a method to draw a number in 0..bound; bound must not be 0
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    // Define a method to pick one item of a non-empty slice
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+// Define the Invariant type, which is a named predicate over the current state and the context
+type Invariant<S, CTX> = (String, Box<dyn Fn(&S, &CTX) -> bool>);
+
+// Define the name of the invariant broken by a machine that init did not leave in a state
+const INITIALIZED: &str = "the machine is initialized";
+
+// Define the Fuzzer struct, which runs random event sequences against fresh machines
+pub struct Fuzzer<
+    S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+    CTX,
+    E: Debug,
+    Err = String,
+> {
+    make_machine: Box<dyn Fn() -> StateMachine<S, CTX, E, Err>>,
+    initial_state: S,
+    generate: Box<dyn Fn(&mut Rng) -> E>,
+    invariants: Vec<Invariant<S, CTX>>,
+    runs: usize,
+    max_events: usize,
+    seed: u64,
+}
+
+impl<S, CTX, E, Err> Fuzzer<S, CTX, E, Err>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug + FsmEnum<S, CTX, E, Err>,
+    E: Debug,
+    Err: Debug + Display,
+{
+    // Define a constructor fuzzing the machines built by `make_machine`, initialized in `initial_state`,
+    // with the events drawn by `generate`
+    pub fn new(
+        make_machine: impl Fn() -> StateMachine<S, CTX, E, Err> + 'static,
+        initial_state: S,
+        generate: impl Fn(&mut Rng) -> E + 'static,
+    ) -> Self {
+        Self {
+            make_machine: Box::new(make_machine),
+            initial_state,
+            generate: Box::new(generate),
+            invariants: Vec::new(),
+            runs: 100,
+            max_events: 50,
+            seed: 0x5EED,
+        }
+    }
+
+    // Define a method to add an invariant, named in failure reports
+    pub fn with_invariant(
+        mut self,
+        name: &str,
+        invariant: impl Fn(&S, &CTX) -> bool + 'static,
+    ) -> Self {
+        self.invariants
+            .push((name.to_string(), Box::new(invariant)));
+        self
+    }
+
+    // Define a method to set the number of sequences to run (100 by default)
+    pub fn with_runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    // Define a method to set the length of each sequence (50 events by default)
+    pub fn with_max_events(mut self, max_events: usize) -> Self {
+        self.max_events = max_events;
+        self
+    }
+
+    // Define a method to set the seed the sequences are drawn from
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    // Define a method to run the sequences, stopping at the first one breaking an invariant
+    pub fn run(&self) -> FuzzReport<S, E> {
+        let mut rng = Rng::new(self.seed);
+        let mut report = FuzzReport {
+            runs: 0,
+            events: 0,
+            visited_states: Vec::new(),
+            transitions: Vec::new(),
+            failure: None,
+        };
+        for _ in 0..self.runs {
+            report.runs += 1;
+            let mut events = Vec::new();
+            if let Some(broken) = self.run_random(&mut rng, &mut events, &mut report) {
+                report.failure = Some(self.shrink(events, broken));
+                break;
+            }
+        }
+        report
+    }
+
+    // Define a helper running one random sequence, recording coverage and keeping the events drawn.
+    // Returns the first broken invariant and the state, if any.
+    fn run_random(
+        &self,
+        rng: &mut Rng,
+        events: &mut Vec<E>,
+        report: &mut FuzzReport<S, E>,
+    ) -> Option<(String, Option<S>)> {
+        let mut machine = (self.make_machine)();
+        let _ = machine.init(self.initial_state.clone());
+        if let Some(broken) = self.check(&machine) {
+            return Some(broken);
+        }
+        report.visit(machine.get_current_state());
+        for _ in 0..self.max_events {
+            let event = (self.generate)(rng);
+            let before = machine.get_current_state().cloned();
+            let _ = machine.process_event(&event);
+            report.events += 1;
+            let after = machine.get_current_state();
+            report.visit(after);
+            if let (Some(from), Some(to)) = (before, after) {
+                if from != *to {
+                    report.cover(from, event_name(&event), to.clone());
+                }
+            }
+            events.push(event);
+            if let Some(broken) = self.check(&machine) {
+                return Some(broken);
+            }
+        }
+        None
+    }
+
+    // Define a helper replaying a sequence on a fresh machine, up to the first broken invariant
+    fn replay(&self, events: &[E]) -> Option<(usize, String, Option<S>)> {
+        let mut machine = (self.make_machine)();
+        let _ = machine.init(self.initial_state.clone());
+        if let Some((invariant, state)) = self.check(&machine) {
+            return Some((0, invariant, state));
+        }
+        for (index, event) in events.iter().enumerate() {
+            let _ = machine.process_event(event);
+            if let Some((invariant, state)) = self.check(&machine) {
+                return Some((index + 1, invariant, state));
+            }
+        }
+        None
+    }
+
+    // Define a helper checking the invariants, returning the name of the first broken one and the state.
+    // Errors returned by the machine are part of normal operation, so only invariants can fail a run,
+    // along with a machine that has no state since init failed.
+    fn check(&self, machine: &StateMachine<S, CTX, E, Err>) -> Option<(String, Option<S>)> {
+        let Some(state) = machine.get_current_state() else {
+            return Some((INITIALIZED.to_string(), None));
+        };
+        self.invariants
+            .iter()
+            .find(|(_, invariant)| !invariant(state, machine.get_context()))
+            .map(|(name, _)| (name.clone(), Some(state.clone())))
+    }
+
+    // Define a helper shrinking a failing sequence: cut it after the failing event, then remove chunks
+    // of events, halving their size down to single events, keeping every removal that still fails.
+    // `broken` is what the random run reported, kept as is if replaying the sequence does not fail.
+    fn shrink(&self, mut events: Vec<E>, broken: (String, Option<S>)) -> FuzzFailure<S, E> {
+        let original_len = events.len();
+        let (mut invariant, mut state) = match self.replay(&events) {
+            Some((length, invariant, state)) => {
+                events.truncate(length);
+                (invariant, state)
+            }
+            None => {
+                return FuzzFailure {
+                    invariant: broken.0,
+                    state: broken.1,
+                    events,
+                    original_len,
+                    reproducible: false,
+                }
+            }
+        };
+        let mut chunk = events.len().max(1);
+        loop {
+            let mut start = 0;
+            while start < events.len() {
+                let end = (start + chunk).min(events.len());
+                let removed: Vec<E> = events.drain(start..end).collect();
+                match self.replay(&events) {
+                    Some((length, broken_invariant, broken_state)) => {
+                        events.truncate(length);
+                        invariant = broken_invariant;
+                        state = broken_state;
+                    }
+                    None => {
+                        events.splice(start..start, removed);
+                        start = end;
+                    }
+                }
+            }
+            if chunk == 1 {
+                break;
+            }
+            chunk /= 2;
+        }
+        FuzzFailure {
+            invariant,
+            state,
+            events,
+            original_len,
+            reproducible: true,
+        }
+    }
+}
+
+// Define the FuzzFailure struct, which is a minimal sequence breaking an invariant
+#[derive(Debug)]
+pub struct FuzzFailure<S, E> {
+    // The name of the invariant that no longer held
+    pub invariant: String,
+    // The state the machine was in when it broke
+    pub state: Option<S>,
+    // The events to process after init to break it; empty if init alone breaks it
+    pub events: Vec<E>,
+    // The length of the random sequence before shrinking
+    pub original_len: usize,
+    // Whether replaying the sequence broke an invariant again; if not, the sequence was not shrunk
+    pub reproducible: bool,
+}
+
+// Define the FuzzReport struct, which is the coverage reached and the failure found, if any
+#[derive(Debug)]
+pub struct FuzzReport<S, E> {
+    // The number of sequences run
+    pub runs: usize,
+    // The number of events processed across all the sequences
+    pub events: usize,
+    // The states the machine was in, in the order they were first seen
+    pub visited_states: Vec<S>,
+    // The (from, event, to) transitions taken, in the order they were first taken
+    pub transitions: Vec<(S, String, S)>,
+    pub failure: Option<FuzzFailure<S, E>>,
+}
+
+impl<S: PartialEq + Clone, E> FuzzReport<S, E> {
+    // Define a method to check that every invariant held
+    pub fn is_ok(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    // Define a helper recording a visited state
+    fn visit(&mut self, state: Option<&S>) {
+        if let Some(state) = state {
+            if !self.visited_states.contains(state) {
+                self.visited_states.push(state.clone());
+            }
+        }
+    }
+
+    // Define a helper recording a transition taken
+    fn cover(&mut self, from: S, event: String, to: S) {
+        let transition = (from, event, to);
+        if !self.transitions.contains(&transition) {
+            self.transitions.push(transition);
+        }
+    }
+}
+
+impl<S: Debug, E: Debug> Display for FuzzReport<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} runs, {} events", self.runs, self.events)?;
+        writeln!(f, "visited states: {:?}", self.visited_states)?;
+        for (from, event, to) in &self.transitions {
+            writeln!(f, "transition: {:?} --{}--> {:?}", from, event, to)?;
+        }
+        if let Some(failure) = &self.failure {
+            write!(
+                f,
+                "invariant {:?} broken in state {:?} after {} events",
+                failure.invariant,
+                failure.state,
+                failure.events.len()
+            )?;
+            if failure.reproducible {
+                writeln!(f, " (shrunk from {}):", failure.original_len)?;
+            } else {
+                writeln!(f, " (not reproducible on replay, not shrunk):")?;
+            }
+            for event in &failure.events {
+                writeln!(f, "  {:?}", event)?;
+            }
+        }
+        Ok(())
+    }
+}
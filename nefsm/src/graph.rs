@@ -0,0 +1,125 @@
+//! Graphviz DOT and Mermaid export of a transition table.
+//!
+//! States are named by their `Debug` output and listed in order of first appearance in the table.
+//! Edges are labelled with the event variant name, followed by `[guard]` when the edge is guarded.
+//! The state passed as `current` is highlighted, and drawn even if no edge of the table reaches it,
+//! which is how `StateMachine::to_dot` and `StateMachine::to_mermaid` render a live machine.
+//!
+//! Mermaid identifiers can only hold letters, digits and underscores, so every other character of a
+//! state name, including `_`, is escaped as its code point between underscores (`A::B` becomes
+//! `A_3a__3a_B`), and such states are declared with their name as label.
+
+use std::fmt::{Debug, Write};
+
+use crate::table::{Transition, TransitionTable};
+
+// Define a function to render the table as a Graphviz digraph
+pub fn to_dot<S: Debug + PartialEq, CTX, E: Debug>(
+    table: &TransitionTable<S, CTX, E>,
+    current: Option<&S>,
+) -> String {
+    let mut out = String::from("digraph fsm {\n");
+    for state in states(table, current) {
+        let name = dot_id(state);
+        if Some(state) == current {
+            let _ = writeln!(out, "    {} [style=filled, fillcolor=lightblue];", name);
+        } else {
+            let _ = writeln!(out, "    {};", name);
+        }
+    }
+    for transition in table.get_transitions() {
+        let _ = writeln!(
+            out,
+            "    {} -> {} [label={}];",
+            dot_id(transition.get_from()),
+            dot_id(transition.get_to()),
+            dot_quote(&edge_label(transition))
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+// Define a function to render the table as a Mermaid stateDiagram-v2
+pub fn to_mermaid<S: Debug + PartialEq, CTX, E: Debug>(
+    table: &TransitionTable<S, CTX, E>,
+    current: Option<&S>,
+) -> String {
+    let mut out = String::from("stateDiagram-v2\n");
+    let edges = states(table, None);
+    for state in states(table, current) {
+        let id = mermaid_id(state);
+        let name = format!("{:?}", state);
+        if id != name {
+            let _ = writeln!(out, "    {} : {}", id, name);
+        } else if !edges.contains(&state) {
+            let _ = writeln!(out, "    {}", id);
+        }
+    }
+    for transition in table.get_transitions() {
+        let _ = writeln!(
+            out,
+            "    {} --> {} : {}",
+            mermaid_id(transition.get_from()),
+            mermaid_id(transition.get_to()),
+            edge_label(transition)
+        );
+    }
+    if let Some(state) = current {
+        let _ = writeln!(out, "    classDef current fill:#add8e6");
+        let _ = writeln!(out, "    class {} current", mermaid_id(state));
+    }
+    out
+}
+
+// Define a helper listing the distinct states of the table in order of first appearance, followed by
+// `current` if no edge mentions it
+fn states<'a, S: PartialEq, CTX, E: Debug>(
+    table: &'a TransitionTable<S, CTX, E>,
+    current: Option<&'a S>,
+) -> Vec<&'a S> {
+    let mut states: Vec<&S> = Vec::new();
+    for transition in table.get_transitions() {
+        for state in [transition.get_from(), transition.get_to()] {
+            if !states.contains(&state) {
+                states.push(state);
+            }
+        }
+    }
+    if let Some(state) = current {
+        if !states.contains(&state) {
+            states.push(state);
+        }
+    }
+    states
+}
+
+fn edge_label<S: PartialEq, CTX, E: Debug>(transition: &Transition<S, CTX, E>) -> String {
+    if transition.has_guard() {
+        format!("{} [guard]", transition.get_event_name())
+    } else {
+        transition.get_event_name().to_string()
+    }
+}
+
+fn dot_id<S: Debug>(state: &S) -> String {
+    dot_quote(&format!("{:?}", state))
+}
+
+fn dot_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Define a helper turning a state into a valid Mermaid identifier, escaping the characters Mermaid
+// does not accept so that different states never share an identifier
+fn mermaid_id<S: Debug>(state: &S) -> String {
+    let mut id = String::new();
+    for c in format!("{:?}", state).chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c);
+        } else {
+            let _ = write!(id, "_{:x}_", c as u32);
+        }
+    }
+    id
+}
@@ -0,0 +1,117 @@
+//! Transition history kept by a `StateMachine`.
+//!
+//! When enabled with `StateMachine::with_history`, every call to `process_event` on an initialized
+//! machine is recorded with its timestamp, the event's `Debug` output, the states before and after,
+//! and the outcome. Only the most recent `capacity` entries are kept.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Display};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Define the Outcome enum, which summarizes how an event was processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    // The event was processed and the machine stayed in the same state
+    Handled,
+    // The event was processed and the machine moved to another state
+    Transition,
+    // Processing returned an error, recorded with its Debug output
+    Error(String),
+}
+
+// Define the HistoryEntry struct, which records a single processed event
+#[derive(Debug, Clone)]
+pub struct HistoryEntry<S> {
+    pub timestamp: SystemTime,
+    pub event: String,
+    pub from: S,
+    pub to: S,
+    pub outcome: Outcome,
+}
+
+impl<S: Debug> Display for HistoryEntry<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let since_epoch = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        write!(
+            f,
+            "{}.{:03} {:?} --{}--> {:?}",
+            since_epoch.as_secs(),
+            since_epoch.subsec_millis(),
+            self.from,
+            self.event,
+            self.to
+        )?;
+        match &self.outcome {
+            Outcome::Handled => write!(f, " (handled)"),
+            Outcome::Transition => write!(f, " (transition)"),
+            Outcome::Error(e) => write!(f, " (error: {})", e),
+        }
+    }
+}
+
+// Define the History struct, a ring buffer holding the most recent entries
+#[derive(Debug, Clone)]
+pub struct History<S> {
+    entries: VecDeque<HistoryEntry<S>>,
+    capacity: usize,
+}
+
+impl<S> History<S> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    // Define a method to record an entry, dropping the oldest one when the buffer is full
+    pub fn record<E: Debug>(&mut self, from: S, to: S, event: &E, outcome: Outcome) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(HistoryEntry {
+            timestamp: SystemTime::now(),
+            event: format!("{:?}", event),
+            from,
+            to,
+            outcome,
+        });
+    }
+
+    // Define a method to iterate over the recorded entries, oldest first
+    pub fn iter(&self) -> impl Iterator<Item = &HistoryEntry<S>> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl<S: Debug> History<S> {
+    // Define a method to dump the history as text, one entry per line, oldest first
+    pub fn dump(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| format!("{}\n", entry))
+            .collect()
+    }
+}
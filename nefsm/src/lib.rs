@@ -8,6 +8,9 @@
 //!
 //! * `FsmEnum` is a trait that defines how to create a new state machine state based on a given
 //!   enum value. This is used to instantiate new state machine states when a state transition occurs.
+//!   It can optionally declare a parent for each state to build hierarchical (nested) states:
+//!   events a state returns `Response::Unhandled` for bubble up to its parent, and transitions only
+//!   exit and enter the states below the least common ancestor of the source and target.
 //!
 //! * `Stateful` is a trait that defines how a state should handle state transition events.
 //!
@@ -15,360 +18,727 @@
 //!   current state, and provides methods to initialize the state machine, process events, and get
 //!   the current state.
 //!
+//! * `TransitionTable` (in the `table` module) declares the (state, event) edges of a machine, each
+//!   with an optional guard and action, which `StateMachine` evaluates before the state's own
+//!   `on_event` and before any `on_exit`. The `graph` module exports a table, or a live machine with
+//!   its current state highlighted, as Graphviz DOT or Mermaid text.
+//!
+//! * `fsm!` is a macro that generates the state enum together with its `FsmEnum` and `Stateful`
+//!   impls from a declarative transition list, for either the `sync` or the `Async` module.
+//!
+//! * `StateMachine::with_history` keeps a bounded log of the processed events, their outcome and the
+//!   states before and after, which can be queried with `history()` and dumped as text.
+//!
+//! * `TransitionObserver` (in the `observer` module) is notified around every state callback, on
+//!   errors and on events that caused no transition. `TracingObserver` reports all of it through
+//!   `tracing`.
+//!
+//! * With the `serde` feature, `StateMachine::snapshot` and `StateMachine::restore` save and resume
+//!   a machine (current state, context and optional per-state data) without re-running `on_enter`.
+//!
+//! * `Async::Stateful::schedule_timers` lets a state schedule timer events that are cancelled when it
+//!   is exited. Time comes from a `Clock` (in the `timer` module), with std, tokio and manual clocks.
+//!
+//! * With the `tokio` feature, `StateMachineHandle` (in the `actor` module) runs an
+//!   `Async::StateMachine` in its own task and lets any number of tasks send events to it, call it
+//!   and query its current state.
+//!
+//! * `Stateful::is_deferred` lets a state hold events back until the machine reaches a state that
+//!   handles them; they are replayed in order after each transition (`with_deferred_events`), and
+//!   the first replayed event to fail is reported by the call that caused the transition.
+//!
+//! * A context implementing `HasOutbox` lets states post follow-up events, processed with
+//!   run-to-completion semantics once `with_event_queue` is enabled.
+//!
+//! * `Error` (in the `error` module) is shared by both modules: an `ErrorKind` together with the
+//!   source state, the state being entered and the event involved. `Response::Error` carries a
+//!   `String` by default, or any error type given as the last type parameter of the traits.
+//!
+//! * `StateMachine::with_transactions` restores the context and the current state when processing an
+//!   event (or `init`) fails, so a rejected transition never leaves the machine half-transitioned.
+//!
+//! * `ParallelStateMachine` runs several `Region`s, each with its own state enum, side by side over the
+//!   context it owns: every event goes to every region, and the states of the regions are read back
+//!   all at once, by position or by state type. A `Region` is a machine that runs against a context it
+//!   is given on every call.
+//!
+//! * `Analyzer` (in the `analysis` module) checks a transition table without running it, reporting
+//!   unreachable and dead-end states, unhandled events, ambiguous edges and `on_enter` cycles.
+//!
+//! * `StateMachine::with_context` changes the context from outside the machine and tells the active
+//!   states through `Stateful::on_context_changed`; `into_parts` hands back the context and the final
+//!   state.
+//!
+//! * `FsmEnum::is_final` marks the states a machine ends in: a finished machine rejects events with
+//!   `ErrorKind::Finished`, and `Async::StateMachine::completion` returns a future resolving with the
+//!   final state and context.
+//!
+//! * `StateMachine::reset` and `reinit` restart a machine, and `CachePolicy` (in the `cache` module)
+//!   decides whether state objects are kept, recreated on every entry or bounded in number.
+//!
+//! * With the `testing` feature, `Scenario` (in the `testing` module) describes a unit test as events
+//!   and expected states, errors and contexts, and prints a trace of the callbacks when a step fails.
+//!
+//! * `Fuzzer` (in the `fuzz` module, also behind `testing`) drives a `sync::StateMachine` through
+//!   random event sequences, checks invariants over the state and context, shrinks failing sequences
+//!   to minimal reproductions and reports the states and transitions covered.
+//!
+//! * `CoverageRecorder` (in the `coverage` module) collects the states, transitions and errors of any
+//!   number of machines (`with_coverage`) and reports the untested edges of a transition table, as
+//!   text or JSON.
+//!
+//! * `StaticStateMachine` (in the `static_dispatch` module) runs a state enum whose variants carry their
+//!   own data and implement `StateEnum`, without hashing, boxing or dynamic dispatch, for hot paths
+//!   that do not need the features of `sync::StateMachine`.
+//!
+//! * `fixed::StateMachine` is the counterpart of `sync::StateMachine` for targets without an allocator:
+//!   the state objects are kept in an array with a slot per state, and states are flat.
+//!
+//! * The `std` feature, on by default, can be disabled for `no_std` targets. Only `core` is then
+//!   used, and the crate is reduced to `fixed::StateMachine`, `StaticStateMachine`, `Response` (in the
+//!   `response` module) and the errors. These are the same with or without `std`: their error text
+//!   defaults to a `&'static str`, while the `sync` and `Async` modules default to a `String`.
+//!
+//! * `sync::StateMachine` and `Async::StateMachine` are the same `engine::StateMachine`, driven through
+//!   a front-end that calls either the blocking or the async state callbacks, so both modules share
+//!   their `Response`, errors and features and behave identically.
+//!
 //! This library is designed to be easy to use and flexible enough to handle a wide variety of
 //! state machine designs.
 //!
 //!
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "tokio")]
+pub mod actor;
+#[cfg(feature = "std")]
+pub mod analysis;
+pub mod cache;
+#[cfg(feature = "std")]
+pub mod completion;
+#[cfg(feature = "std")]
+pub mod coverage;
+#[cfg(feature = "std")]
+pub mod engine;
+pub mod error;
+pub mod fixed;
+#[cfg(feature = "testing")]
+pub mod fuzz;
+#[cfg(feature = "std")]
+pub mod graph;
+#[cfg(feature = "std")]
+pub mod history;
+#[cfg(feature = "std")]
+mod macros;
+#[cfg(feature = "std")]
+pub mod observer;
+#[cfg(feature = "std")]
+pub mod outbox;
+pub mod response;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod static_dispatch;
+#[cfg(feature = "std")]
+pub mod table;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "std")]
+pub mod timer;
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+}
+
+#[cfg(feature = "std")]
 pub mod sync {
-    use std::fmt::Debug;
-    use std::{collections::HashMap, hash::Hash};
+    use std::fmt::{Debug, Display};
+    use std::future::{ready, Future};
+    use std::hash::Hash;
+
+    use crate::engine::{self, block_on, FrontEnd, ParallelFrontEnd, RegionFuture, SyncFrontEnd};
+    #[cfg(feature = "serde")]
+    use crate::snapshot::Snapshot;
+    use crate::timer::Timers;
+
+    // Define the Response, Error and ErrorKind types of this module, whose errors are a String by default
+    pub type Response<S, Err = String> = crate::response::Response<S, Err>;
+    pub type Error<S, Err = String> = crate::error::Error<S, Err>;
+    pub type ErrorKind<S, Err = String> = crate::error::ErrorKind<S, Err>;
 
     // Define the FsmEnum trait, which is used to create new state objects
-    pub trait FsmEnum<S, CTX, E> {
-        fn create(enum_value: &S) -> Box<dyn Stateful<S, CTX, E> + Send>;
+    pub trait FsmEnum<S, CTX, E, Err = String> {
+        fn create(enum_value: &S) -> Box<dyn Stateful<S, CTX, E, Err> + Send>;
+
+        // Define the parent of a state. Events a state leaves unhandled bubble up to its parent,
+        // and transitions only exit/enter the states below the least common ancestor.
+        // The parent relation must form a tree; flat machines keep the default of no parent.
+        fn parent(_enum_value: &S) -> Option<S> {
+            None
+        }
+
+        // Define whether a state is final. Once the machine is in a final state it is finished and
+        // rejects any further event.
+        fn is_final(_enum_value: &S) -> bool {
+            false
+        }
     }
 
     // Define the Stateful trait, which contains the event handling methods for each state
-    pub trait Stateful<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug> {
-        fn on_enter(&mut self, context: &mut CTX) -> Response<S>;
-        fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S>;
+    pub trait Stateful<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug, Err = String> {
+        fn on_enter(&mut self, context: &mut CTX) -> Response<S, Err>;
+        fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S, Err>;
         fn on_exit(&mut self, context: &mut CTX);
+
+        // Define a hook telling whether this state defers the event until after the next transition
+        fn is_deferred(&self, _event: &E, _context: &CTX) -> bool {
+            false
+        }
+
+        // Define a hook called when the context was changed through `StateMachine::with_context`
+        // while this state, or one of its descendants, is the current state
+        fn on_context_changed(&mut self, _context: &mut CTX) {}
+
+        // Define a hook returning this state's data to include in a snapshot, if any
+        #[cfg(feature = "serde")]
+        fn save_data(&self) -> Option<Vec<u8>> {
+            None
+        }
+
+        // Define a hook receiving the data saved by `save_data` when a machine is restored
+        #[cfg(feature = "serde")]
+        fn restore_data(&mut self, _data: &[u8]) {}
     }
 
     // Define the EventHandler trait, which is used to handle global events
-    pub trait EventHandler<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug> {
-        fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S>;
+    pub trait EventHandler<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug, Err = String> {
+        fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S, Err>;
     }
 
-    // Define the Response enum, which is used to handle state transitions
-    pub enum Response<S> {
-        Handled,
-        Error(String),
-        Transition(S),
-    }
+    // Define the StateMachine type, which is the shared engine running the sync state callbacks
+    pub type StateMachine<S, CTX, E, Err = String> =
+        engine::StateMachine<S, CTX, E, Err, SyncFrontEnd>;
+
+    // Implement the FrontEnd trait by calling the sync callbacks, whose results are ready right away
+    impl<S, CTX, E, Err> FrontEnd<S, CTX, E, Err> for SyncFrontEnd
+    where
+        S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+        E: Debug,
+    {
+        type State = dyn Stateful<S, CTX, E, Err> + Send;
+        type Handler = dyn EventHandler<S, CTX, E, Err> + Send;
+
+        fn create(state: &S) -> Box<Self::State> {
+            S::create(state)
+        }
 
-    // Define the Error enum, which is used to handle errors
-    #[derive(Debug)]
-    pub enum Error {
-        StateNotFound(String),
-        StateInvalid(String),
-        InvalidEvent(String),
-        StateMachineNotInitialized,
-        InternalError(String),
-    }
+        fn parent(state: &S) -> Option<S> {
+            S::parent(state)
+        }
+
+        fn is_final(state: &S) -> bool {
+            S::is_final(state)
+        }
+
+        fn on_enter<'a>(
+            state: &'a mut Self::State,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            ready(state.on_enter(context))
+        }
+
+        fn on_event<'a>(
+            state: &'a mut Self::State,
+            event: &'a E,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            ready(state.on_event(event, context))
+        }
+
+        fn on_exit<'a>(
+            state: &'a mut Self::State,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = ()> {
+            state.on_exit(context);
+            ready(())
+        }
+
+        fn on_global_event<'a>(
+            handler: &'a mut Self::Handler,
+            event: &'a E,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            ready(handler.on_event(event, context))
+        }
+
+        fn is_deferred(state: &Self::State, event: &E, context: &CTX) -> bool {
+            state.is_deferred(event, context)
+        }
+
+        fn on_context_changed(state: &mut Self::State, context: &mut CTX) {
+            state.on_context_changed(context)
+        }
+
+        // Sync states cannot schedule timers
+        fn schedule_timers(_state: &mut Self::State, _context: &CTX, _timers: &mut Timers<E>) {}
+
+        #[cfg(feature = "serde")]
+        fn save_data(state: &Self::State) -> Option<Vec<u8>> {
+            state.save_data()
+        }
 
-    // Define the StateMachine struct, which represents the finite state machine
-    pub struct StateMachine<S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E>, CTX, E: Debug> {
-        states: HashMap<S, Box<dyn Stateful<S, CTX, E> + Send>>,
-        current_state: Option<S>,
-        context: CTX,
-        global_event_handler: Option<Box<dyn EventHandler<S, CTX, E> + Send>>,
+        #[cfg(feature = "serde")]
+        fn restore_data(state: &mut Self::State, data: &[u8]) {
+            state.restore_data(data)
+        }
     }
 
-    // Implement methods for the StateMachine struct
-    impl<S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E>, CTX, E: Debug> StateMachine<S, CTX, E> {
+    // Implement the methods of the StateMachine that block until the engine is done
+    impl<
+            S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+            CTX,
+            E: Debug,
+            Err: Debug + Display,
+        > StateMachine<S, CTX, E, Err>
+    {
         // Define a constructor for the StateMachine struct
-        pub fn new(context: CTX, handler: Option<Box<dyn EventHandler<S, CTX, E> + Send>>) -> Self {
-            let states = HashMap::<S, Box<dyn Stateful<S, CTX, E> + Send>>::new();
-            Self {
-                states,
-                current_state: None,
-                context,
-                global_event_handler: handler,
-            }
+        pub fn new(
+            context: CTX,
+            handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>,
+        ) -> Self {
+            Self::create(context, handler)
         }
 
-        // Define a method to get the current state
-        pub fn get_current_state(&self) -> Option<&S> {
-            self.current_state.as_ref()
+        // Define a method to initialize the state machine with an initial state
+        pub fn init(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+            block_on(self.core_init(initial_state))
         }
 
-        // Define a method to get a reference to the context
-        pub fn get_context(&self) -> &CTX {
-            &self.context
+        // Define a method to exit the current state and its ancestors, innermost first, and forget the
+        // current state and the deferred events, so that the machine can be initialized again
+        pub fn reset(&mut self) {
+            block_on(self.core_reset())
         }
 
-        // Define a method to initialize the state machine with an initial state
-        // Note how the state objects are cached in a HashMap and not recreated every time we transition to this event.
-        pub fn init(&mut self, initial_state: S) -> Result<(), Error> {
-            if self.current_state.is_none() {
-                let mut next_state = Some(initial_state);
-                // TODO: maybe CTX should implement Clone to prevent side effects (clone self.context here and set later, according to state)
-                loop {
-                    let current_state_ref = next_state.as_ref().unwrap();
-                    let state = if let Some(existing_state) = self.states.get_mut(current_state_ref)
-                    {
-                        existing_state
-                    } else {
-                        let new_state = S::create(current_state_ref);
-                        let current_state_clone = next_state.clone().unwrap();
-                        self.states.entry(current_state_clone).or_insert(new_state)
-                    };
-
-                    match state.on_enter(&mut self.context) {
-                        Response::Handled => break,
-                        Response::Error(e) => return Err(Error::StateInvalid(e)),
-                        Response::Transition(s) => next_state = Some(s),
-                    }
-                }
-                self.current_state = next_state;
-            }
-            Ok(())
+        // Define a method to reset the machine and initialize it again in `initial_state`
+        pub fn reinit(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+            self.reset();
+            self.init(initial_state)
         }
 
         // Define a method to process events and transition between states
-        pub fn process_event(&mut self, event: &E) -> Result<(), Error> {
-            let c_state = match &self.current_state {
-                Some(state) => state,
-                None => return Err(Error::StateMachineNotInitialized),
-            };
-
-            if let Some(global_handler) = &mut self.global_event_handler {
-                match global_handler.on_event(event, &mut self.context) {
-                    Response::Handled => {}
-                    Response::Error(s) => return Err(Error::InvalidEvent(s)),
-                    Response::Transition(new_state) => {
-                        if new_state != *c_state {
-                            return self.transition_to(new_state);
-                        }
-                    }
-                }
-            }
-
-            let current_state_ref = self.current_state.as_ref().unwrap();
-            let state = if let Some(existing_state) = self.states.get_mut(current_state_ref) {
-                existing_state
-            } else {
-                let new_state = S::create(current_state_ref);
-                let current_state_clone = self.current_state.clone().unwrap();
-                self.states.entry(current_state_clone).or_insert(new_state)
-            };
-            match state.on_event(event, &mut self.context) {
-                Response::Handled => Ok(()),
-                Response::Error(s) => Err(Error::InvalidEvent(s)),
-                Response::Transition(new_state) => {
-                    if new_state != *c_state {
-                        self.transition_to(new_state)?;
-                    }
-                    Ok(())
-                }
-            }
-        }
-
-        // Define a method to handle state transitions
-        fn transition_to(&mut self, new_state: S) -> Result<(), Error> {
-            let c_state = self.current_state.as_ref().unwrap();
-            let state = self.states.get_mut(&c_state).unwrap();
-            state.on_exit(&mut self.context);
-
-            let mut next_state = Some(new_state.clone());
-            loop {
-                let current_state_ref = next_state.as_ref().unwrap();
-                let s = if let Some(existing_state) = self.states.get_mut(current_state_ref) {
-                    existing_state
-                } else {
-                    let new_state = S::create(current_state_ref);
-                    let current_state_clone = next_state.clone().unwrap();
-                    self.states.entry(current_state_clone).or_insert(new_state)
-                };
-                match s.on_enter(&mut self.context) {
-                    Response::Handled => {
-                        break;
-                    }
-                    Response::Error(e) => return Err(Error::StateInvalid(e)),
-                    Response::Transition(s) => {
-                        if s == *next_state.as_ref().unwrap() {
-                            break;
-                        } else {
-                            next_state = Some(s);
-                        }
-                    }
-                }
-            }
-
-            self.current_state = next_state;
-
-            Ok(())
+        pub fn process_event(&mut self, event: &E) -> Result<(), Error<S, Err>> {
+            block_on(self.core_process_event(event))
+        }
+
+        // Define a constructor resuming a machine from a snapshot; unlike init, no on_enter is called
+        #[cfg(feature = "serde")]
+        pub fn restore(
+            snapshot: Snapshot<S, CTX>,
+            handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>,
+        ) -> Self
+        where
+            CTX: Clone,
+        {
+            Self::create_from_snapshot(snapshot, handler)
+        }
+    }
+
+    // Define the Region type, which is a machine running its states against a context it does not own
+    pub type Region<S, CTX, E, Err = String> = engine::Region<S, CTX, E, Err, SyncFrontEnd>;
+
+    // Implement the methods of the Region that block until the engine is done
+    impl<
+            S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+            CTX,
+            E: Debug,
+            Err: Debug + Display,
+        > Region<S, CTX, E, Err>
+    {
+        // Define a constructor for the Region struct
+        pub fn new(handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>) -> Self {
+            Self::create(handler)
+        }
+
+        // Define a method to initialize the region with an initial state, entering it with `context`
+        pub fn init(&mut self, context: &mut CTX, initial_state: S) -> Result<(), Error<S, Err>> {
+            block_on(self.core_init(context, initial_state))
+        }
+
+        // Define a method to process events against `context` and transition between states
+        pub fn process_event(&mut self, context: &mut CTX, event: &E) -> Result<(), Error<S, Err>> {
+            block_on(self.core_process_event(context, event))
+        }
+    }
+
+    // Implement the ParallelFrontEnd trait by running the region to completion, so that the future handed
+    // back is ready and needs no more than the result to be Send
+    impl<S, CTX, E, Err> ParallelFrontEnd<S, CTX, E, Err> for SyncFrontEnd
+    where
+        S: Hash + PartialEq + Eq + Clone + Send + FsmEnum<S, CTX, E, Err>,
+        E: Debug,
+        Err: Debug + Display + Send,
+    {
+        fn init_region<'a>(
+            region: &'a mut Region<S, CTX, E, Err>,
+            context: &'a mut CTX,
+            initial_state: S,
+        ) -> RegionFuture<'a, S, Err> {
+            Box::pin(ready(region.init(context, initial_state)))
+        }
+
+        fn process_region_event<'a>(
+            region: &'a mut Region<S, CTX, E, Err>,
+            context: &'a mut CTX,
+            event: &'a E,
+        ) -> RegionFuture<'a, S, Err> {
+            Box::pin(ready(region.process_event(context, event)))
+        }
+    }
+
+    // Define the ParallelStateMachine type, which runs several sync regions side by side over the context it owns
+    pub type ParallelStateMachine<CTX, E, Err = String> =
+        engine::ParallelStateMachine<CTX, E, Err, SyncFrontEnd>;
+
+    // Implement the methods of the ParallelStateMachine that block until every region is done
+    impl<CTX: Send + 'static, E: Debug + 'static, Err: Debug + Display + Send + 'static>
+        ParallelStateMachine<CTX, E, Err>
+    {
+        // Define a method to initialize every region, stopping at the first one that fails
+        pub fn init(&mut self) -> Result<(), Error<String, Err>> {
+            block_on(self.core_init())
+        }
+
+        // Define a method to process an event in every region. All regions see the event even if one fails;
+        // the first error is returned.
+        pub fn process_event(&mut self, event: &E) -> Result<(), Error<String, Err>> {
+            block_on(self.core_process_event(event))
         }
     }
 }
+#[cfg(feature = "std")]
+#[allow(non_snake_case)]
 pub mod Async {
-    use std::fmt::Debug;
-    use std::{collections::HashMap, hash::Hash};
+    use std::fmt::{Debug, Display};
+    use std::future::Future;
+    use std::hash::Hash;
+
+    use crate::completion::{self, Completion};
+    use crate::engine::{self, AsyncFrontEnd, FrontEnd, ParallelFrontEnd, RegionFuture};
+    #[cfg(feature = "serde")]
+    use crate::snapshot::Snapshot;
+    use crate::timer::{Clock, Sleep, Timers};
+    use std::sync::Arc;
 
     use async_trait::async_trait;
 
+    // Define the Response, Error and ErrorKind types of this module, whose errors are a String by default
+    pub type Response<S, Err = String> = crate::response::Response<S, Err>;
+    pub type Error<S, Err = String> = crate::error::Error<S, Err>;
+    pub type ErrorKind<S, Err = String> = crate::error::ErrorKind<S, Err>;
+
     // Define the FsmEnum trait, which is used to create new state objects
-    pub trait FsmEnum<S, CTX, E> {
-        fn create(enum_value: &S) -> Box<dyn Stateful<S, CTX, E> + Send>;
-    }
+    pub trait FsmEnum<S, CTX, E, Err = String> {
+        fn create(enum_value: &S) -> Box<dyn Stateful<S, CTX, E, Err> + Send>;
+
+        // Define the parent of a state. Events a state leaves unhandled bubble up to its parent,
+        // and transitions only exit/enter the states below the least common ancestor.
+        // The parent relation must form a tree; flat machines keep the default of no parent.
+        fn parent(_enum_value: &S) -> Option<S> {
+            None
+        }
 
-    // Define the EventHandler trait for handling global events
-    #[async_trait]
-    pub trait EventHandler<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug> {
-        async fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S>;
+        // Define whether a state is final. Once the machine is in a final state it is finished and
+        // rejects any further event.
+        fn is_final(_enum_value: &S) -> bool {
+            false
+        }
     }
 
     // Define the Stateful trait, which contains the event handling methods for each state
     #[async_trait]
-    pub trait Stateful<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug> {
-        async fn on_enter(&mut self, context: &mut CTX) -> Response<S>;
-        async fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S>;
+    pub trait Stateful<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug, Err = String> {
+        async fn on_enter(&mut self, context: &mut CTX) -> Response<S, Err>;
+        async fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S, Err>;
         async fn on_exit(&mut self, context: &mut CTX);
-    }
 
-    // Define the Response enum, which is used to handle state transitions
-    pub enum Response<S> {
-        Handled,
-        Error(String),
-        Transition(S),
+        // Define a hook telling whether this state defers the event until after the next transition
+        fn is_deferred(&self, _event: &E, _context: &CTX) -> bool {
+            false
+        }
+
+        // Define a hook called when the context was changed through `StateMachine::with_context`
+        // while this state, or one of its descendants, is the current state
+        fn on_context_changed(&mut self, _context: &mut CTX) {}
+
+        // Define a hook called right after on_enter succeeds, to schedule timer events owned by this state.
+        // They are cancelled when the state is exited.
+        fn schedule_timers(&mut self, _context: &CTX, _timers: &mut Timers<E>) {}
+
+        // Define a hook returning this state's data to include in a snapshot, if any
+        #[cfg(feature = "serde")]
+        fn save_data(&self) -> Option<Vec<u8>> {
+            None
+        }
+
+        // Define a hook receiving the data saved by `save_data` when a machine is restored
+        #[cfg(feature = "serde")]
+        fn restore_data(&mut self, _data: &[u8]) {}
     }
 
-    // Define the Error enum, which is used to handle errors
-    #[derive(Debug)]
-    pub enum Error {
-        StateNotFound(String),
-        StateInvalid(String),
-        InvalidEvent(String),
-        StateMachineNotInitialized,
-        InternalError(String),
+    // Define the EventHandler trait for handling global events
+    #[async_trait]
+    pub trait EventHandler<S: Hash + PartialEq + Eq + Clone, CTX, E: Debug, Err = String> {
+        async fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<S, Err>;
     }
 
-    // Define the StateMachine struct, which represents the finite state machine
-    pub struct StateMachine<S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E>, CTX, E: Debug> {
-        states: HashMap<S, Box<dyn Stateful<S, CTX, E> + Send>>,
-        current_state: Option<S>,
-        context: CTX,
-        global_event_handler: Option<Box<dyn EventHandler<S, CTX, E> + Send>>,
+    // Define the StateMachine type, which is the shared engine awaiting the async state callbacks
+    pub type StateMachine<S, CTX, E, Err = String> =
+        engine::StateMachine<S, CTX, E, Err, AsyncFrontEnd>;
+
+    // Implement the FrontEnd trait by handing the futures of the async callbacks to the engine
+    impl<S, CTX, E, Err> FrontEnd<S, CTX, E, Err> for AsyncFrontEnd
+    where
+        S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+        E: Debug,
+    {
+        type State = dyn Stateful<S, CTX, E, Err> + Send;
+        type Handler = dyn EventHandler<S, CTX, E, Err> + Send;
+
+        fn create(state: &S) -> Box<Self::State> {
+            S::create(state)
+        }
+
+        fn parent(state: &S) -> Option<S> {
+            S::parent(state)
+        }
+
+        fn is_final(state: &S) -> bool {
+            S::is_final(state)
+        }
+
+        fn on_enter<'a>(
+            state: &'a mut Self::State,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            state.on_enter(context)
+        }
+
+        fn on_event<'a>(
+            state: &'a mut Self::State,
+            event: &'a E,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            state.on_event(event, context)
+        }
+
+        fn on_exit<'a>(
+            state: &'a mut Self::State,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = ()> {
+            state.on_exit(context)
+        }
+
+        fn on_global_event<'a>(
+            handler: &'a mut Self::Handler,
+            event: &'a E,
+            context: &'a mut CTX,
+        ) -> impl Future<Output = Response<S, Err>> {
+            handler.on_event(event, context)
+        }
+
+        fn is_deferred(state: &Self::State, event: &E, context: &CTX) -> bool {
+            state.is_deferred(event, context)
+        }
+
+        fn on_context_changed(state: &mut Self::State, context: &mut CTX) {
+            state.on_context_changed(context)
+        }
+
+        fn schedule_timers(state: &mut Self::State, context: &CTX, timers: &mut Timers<E>) {
+            state.schedule_timers(context, timers)
+        }
+
+        #[cfg(feature = "serde")]
+        fn save_data(state: &Self::State) -> Option<Vec<u8>> {
+            state.save_data()
+        }
+
+        #[cfg(feature = "serde")]
+        fn restore_data(state: &mut Self::State, data: &[u8]) {
+            state.restore_data(data)
+        }
     }
 
-    // Implement methods for the StateMachine struct
-    impl<S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E>, CTX, E: Debug> StateMachine<S, CTX, E> {
+    // Implement the async methods of the StateMachine, and the ones for timers and completions
+    impl<
+            S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+            CTX,
+            E: Debug,
+            Err: Debug + Display,
+        > StateMachine<S, CTX, E, Err>
+    {
         // Define a constructor for the StateMachine struct
         pub fn new(
             context: CTX,
-            global_handler: Option<Box<dyn EventHandler<S, CTX, E> + Send>>,
+            global_handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>,
         ) -> Self {
-            Self {
-                states: HashMap::new(),
-                current_state: None,
-                context,
-                global_event_handler: global_handler,
-            }
+            Self::create(context, global_handler)
         }
 
-        // Define a method to get the current state
-        pub fn get_current_state(&self) -> Option<&S> {
-            self.current_state.as_ref()
+        // Define a method to set the clock used by state timers
+        pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+            self.set_clock(clock);
+            self
         }
 
-        // Define a method to get a reference to the context
-        pub fn get_context(&self) -> &CTX {
-            &self.context
+        // Define a method to get a future resolving with the final state and a clone of the context once the
+        // machine finishes, or right away if it already has
+        pub fn completion(&mut self) -> Completion<S, CTX>
+        where
+            S: Send + 'static,
+            CTX: Clone + Send + 'static,
+        {
+            let (sender, future) = completion::completion();
+            self.add_completion(Box::new(move |state: &S, context: &CTX| {
+                sender.complete(state.clone(), context.clone())
+            }));
+            future
+        }
+
+        // Define a method to get the number of timers waiting to fire
+        pub fn pending_timers(&self) -> usize {
+            self.timer_count()
+        }
+
+        // Define a method to get a future resolving when the earliest pending timer is due.
+        // The future does not borrow the machine, so it can be awaited alongside other event sources,
+        // after which `fire_due_timers` delivers the events. It never resolves if no timer is pending.
+        pub fn next_timer(&self) -> Sleep {
+            self.sleep_until_next_timer()
+        }
+
+        // Define a method to process the events of every due timer, earliest first.
+        // Returns how many timers fired.
+        pub async fn fire_due_timers(&mut self) -> Result<usize, Error<S, Err>> {
+            self.core_fire_due_timers().await
         }
 
         // Define a method to initialize the state machine with an initial state
-        pub async fn init(&mut self, initial_state: S) -> Result<(), Error> {
-            if self.current_state.is_none() {
-                let mut next_state = Some(initial_state);
-                // TODO: maybe CTX should implement Clone to prevent side effects (clone self.context here and set later, according to state)
-                loop {
-                    let current_state_ref = next_state.as_ref().unwrap();
-                    let state = if let Some(existing_state) = self.states.get_mut(current_state_ref)
-                    {
-                        existing_state
-                    } else {
-                        let new_state = S::create(current_state_ref);
-                        let current_state_clone = next_state.clone().unwrap();
-                        self.states.entry(current_state_clone).or_insert(new_state)
-                    };
-
-                    match state.on_enter(&mut self.context).await {
-                        Response::Handled => break,
-                        Response::Error(e) => return Err(Error::StateInvalid(e)),
-                        Response::Transition(s) => next_state = Some(s),
-                    }
-                }
-                self.current_state = next_state;
-            }
-            Ok(())
+        pub async fn init(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+            self.core_init(initial_state).await
+        }
+
+        // Define a method to exit the current state and its ancestors, innermost first, and forget the
+        // current state, the deferred events and the pending timers, so that the machine can be initialized again
+        pub async fn reset(&mut self) {
+            self.core_reset().await
         }
+
+        // Define a method to reset the machine and initialize it again in `initial_state`
+        pub async fn reinit(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+            self.reset().await;
+            self.init(initial_state).await
+        }
+
         // Define a method to process events and transition between states
-        pub async fn process_event(&mut self, event: &E) -> Result<(), Error> {
-            let c_state = match &self.current_state {
-                Some(state) => state,
-                None => return Err(Error::StateMachineNotInitialized),
-            };
-
-            if let Some(ref mut handler) = self.global_event_handler {
-                match handler.on_event(event, &mut self.context).await {
-                    Response::Handled => {}
-                    Response::Error(s) => return Err(Error::InvalidEvent(s)),
-                    Response::Transition(new_state) => {
-                        if new_state != *c_state {
-                            self.transition_to(new_state).await;
-                            return Ok(());
-                        }
-                    }
-                }
-            }
-
-            let current_state_ref = self.current_state.as_ref().unwrap();
-            let state = if let Some(existing_state) = self.states.get_mut(current_state_ref) {
-                existing_state
-            } else {
-                let new_state = S::create(current_state_ref);
-                let current_state_clone = self.current_state.clone().unwrap();
-                self.states.entry(current_state_clone).or_insert(new_state)
-            };
-
-            match state.on_event(event, &mut self.context).await {
-                Response::Handled => Ok(()),
-                Response::Error(s) => Err(Error::InvalidEvent(s)),
-                Response::Transition(new_state) => {
-                    if new_state != *c_state {
-                        return self.transition_to(new_state).await;
-                    }
-                    Ok(())
-                }
-            }
-        }
-
-        async fn transition_to(&mut self, new_state: S) -> Result<(), Error> {
-            let c_state = self.current_state.as_ref().unwrap();
-            let state = self.states.get_mut(&c_state).unwrap();
-            state.on_exit(&mut self.context).await;
-
-            let mut next_state = Some(new_state.clone());
-            loop {
-                let current_state_ref = next_state.as_ref().unwrap();
-                let s = if let Some(existing_state) = self.states.get_mut(current_state_ref) {
-                    existing_state
-                } else {
-                    let new_state = S::create(current_state_ref);
-                    let current_state_clone = next_state.clone().unwrap();
-                    self.states.entry(current_state_clone).or_insert(new_state)
-                };
-
-                match s.on_enter(&mut self.context).await {
-                    Response::Handled => {
-                        break;
-                    }
-                    Response::Error(e) => return Err(Error::StateInvalid(e)),
-                    Response::Transition(s) => {
-                        if s == *current_state_ref {
-                            break;
-                        } else {
-                            next_state = Some(s);
-                        }
-                    }
-                }
-            }
-
-            self.current_state = next_state;
-
-            Ok(())
+        pub async fn process_event(&mut self, event: &E) -> Result<(), Error<S, Err>> {
+            self.core_process_event(event).await
+        }
+
+        // Define a constructor resuming a machine from a snapshot; unlike init, no on_enter is called
+        #[cfg(feature = "serde")]
+        pub fn restore(
+            snapshot: Snapshot<S, CTX>,
+            handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>,
+        ) -> Self
+        where
+            CTX: Clone,
+        {
+            Self::create_from_snapshot(snapshot, handler)
+        }
+    }
+
+    // Define the Region type, which is a machine running its states against a context it does not own
+    pub type Region<S, CTX, E, Err = String> = engine::Region<S, CTX, E, Err, AsyncFrontEnd>;
+
+    // Implement the async methods of the Region
+    impl<
+            S: Hash + PartialEq + Eq + Clone + FsmEnum<S, CTX, E, Err>,
+            CTX,
+            E: Debug,
+            Err: Debug + Display,
+        > Region<S, CTX, E, Err>
+    {
+        // Define a constructor for the Region struct
+        pub fn new(global_handler: Option<Box<dyn EventHandler<S, CTX, E, Err> + Send>>) -> Self {
+            Self::create(global_handler)
+        }
+
+        // Define a method to initialize the region with an initial state, entering it with `context`
+        pub async fn init(
+            &mut self,
+            context: &mut CTX,
+            initial_state: S,
+        ) -> Result<(), Error<S, Err>> {
+            self.core_init(context, initial_state).await
+        }
+
+        // Define a method to process events against `context` and transition between states
+        pub async fn process_event(
+            &mut self,
+            context: &mut CTX,
+            event: &E,
+        ) -> Result<(), Error<S, Err>> {
+            self.core_process_event(context, event).await
+        }
+    }
+
+    // Implement the ParallelFrontEnd trait by boxing the futures of the region, which are Send as long as
+    // the states and events can be shared between threads
+    impl<S, CTX, E, Err> ParallelFrontEnd<S, CTX, E, Err> for AsyncFrontEnd
+    where
+        S: Hash + PartialEq + Eq + Clone + Send + Sync + FsmEnum<S, CTX, E, Err>,
+        CTX: Send,
+        E: Debug + Send + Sync,
+        Err: Debug + Display + Send,
+    {
+        fn init_region<'a>(
+            region: &'a mut Region<S, CTX, E, Err>,
+            context: &'a mut CTX,
+            initial_state: S,
+        ) -> RegionFuture<'a, S, Err> {
+            Box::pin(region.init(context, initial_state))
+        }
+
+        fn process_region_event<'a>(
+            region: &'a mut Region<S, CTX, E, Err>,
+            context: &'a mut CTX,
+            event: &'a E,
+        ) -> RegionFuture<'a, S, Err> {
+            Box::pin(region.process_event(context, event))
+        }
+    }
+
+    // Define the ParallelStateMachine type, which runs several async regions side by side over the context it owns
+    pub type ParallelStateMachine<CTX, E, Err = String> =
+        engine::ParallelStateMachine<CTX, E, Err, AsyncFrontEnd>;
+
+    // Implement the async methods of the ParallelStateMachine
+    impl<CTX: Send + 'static, E: Debug + 'static, Err: Debug + Display + Send + 'static>
+        ParallelStateMachine<CTX, E, Err>
+    {
+        // Define a method to initialize every region, stopping at the first one that fails
+        pub async fn init(&mut self) -> Result<(), Error<String, Err>> {
+            self.core_init().await
+        }
+
+        // Define a method to process an event in every region. All regions see the event even if one fails;
+        // the first error is returned.
+        pub async fn process_event(&mut self, event: &E) -> Result<(), Error<String, Err>> {
+            self.core_process_event(event).await
         }
     }
 }
@@ -0,0 +1,216 @@
+// Declarative state machine definitions.
+
+/// The `fsm!` macro generates the state enum, its `FsmEnum` impl and a `Stateful` impl for either the
+/// `sync` or the `Async` module from a transition list. The enum itself is used as the state object,
+/// so no per-state struct has to be written by hand.
+///
+/// ```
+/// #[derive(Debug)]
+/// pub enum CallEvent { Dial, Answer, HangUp }
+///
+/// pub struct CallContext { pub calls: u32 }
+///
+/// nefsm::fsm! {
+///     sync;
+///     pub enum CallState { Idle, Dialing, Connected }
+///     context: CallContext;
+///     event: CallEvent;
+///     transitions {
+///         Idle + Dial => Dialing,
+///         Dialing + Answer => Connected / |ctx, _event| ctx.calls += 1,
+///         Connected + HangUp => Idle,
+///     }
+///     on_enter {
+///         Connected => |_ctx| nefsm::sync::Response::Handled,
+///     }
+/// }
+///
+/// let mut sm = nefsm::sync::StateMachine::new(CallContext { calls: 0 }, None);
+/// sm.init(CallState::Idle).unwrap();
+/// sm.process_event(&CallEvent::Dial).unwrap();
+/// sm.process_event(&CallEvent::Answer).unwrap();
+/// assert_eq!(sm.get_current_state(), Some(&CallState::Connected));
+/// assert_eq!(sm.get_context().calls, 1);
+/// ```
+///
+/// * `sync` or `Async` selects which module the impls are generated for.
+/// * The enum always derives `Debug`, `Clone`, `PartialEq`, `Eq` and `Hash`; other attributes are kept.
+/// * Events are matched by variant name, ignoring any payload.
+/// * `/ action` runs a `Fn(&mut CTX, &E)` before the transition is taken.
+/// * The optional `parents { Child => Parent, .. }` block declares hierarchical states.
+/// * The optional `final { State, .. }` block declares the final states.
+/// * The optional `on_enter { State => hook, .. }` block takes `Fn(&mut CTX) -> Response<S>` hooks, and
+///   `on_exit { State => hook, .. }` takes `Fn(&mut CTX)` hooks.
+/// * Events without a matching transition return `Response::Unhandled`.
+#[macro_export]
+macro_rules! fsm {
+    (
+        $mode:ident;
+        $(#[$meta:meta])*
+        $vis:vis enum $state:ident { $($variant:ident),* $(,)? }
+        context: $ctx:ty;
+        event: $event:ident;
+        $(parents { $($child:ident => $parent:ident),* $(,)? })?
+        $(final { $($final:ident),* $(,)? })?
+        transitions { $($transitions:tt)* }
+        $(on_enter { $($enter:tt)* })?
+        $(on_exit { $($exit:tt)* })?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        $vis enum $state {
+            $($variant),*
+        }
+
+        $crate::__fsm_impl! {
+            $mode $state, $ctx, $event;
+            parents { $($($child => $parent),*)? }
+            final { $($($final),*)? }
+            transitions { $($transitions)* }
+            on_enter { $($($enter)*)? }
+            on_exit { $($($exit)*)? }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fsm_impl {
+    (
+        sync $state:ident, $ctx:ty, $event:ident;
+        parents { $($child:ident => $parent:ident),* }
+        final { $($final:ident),* }
+        transitions { $($from:ident + $ev:ident => $to:ident $(/ $action:expr)?),* $(,)? }
+        on_enter { $($enter_state:ident => $enter:expr),* $(,)? }
+        on_exit { $($exit_state:ident => $exit:expr),* $(,)? }
+    ) => {
+        impl $crate::sync::FsmEnum<$state, $ctx, $event> for $state {
+            fn create(enum_value: &$state) -> Box<dyn $crate::sync::Stateful<$state, $ctx, $event> + Send> {
+                Box::new(enum_value.clone())
+            }
+
+            fn parent(enum_value: &$state) -> Option<$state> {
+                #[allow(unreachable_patterns)]
+                match enum_value {
+                    $($state::$child => Some($state::$parent),)*
+                    _ => None,
+                }
+            }
+
+            fn is_final(enum_value: &$state) -> bool {
+                #[allow(unreachable_patterns)]
+                match enum_value {
+                    $($state::$final => true,)*
+                    _ => false,
+                }
+            }
+        }
+
+        impl $crate::sync::Stateful<$state, $ctx, $event> for $state {
+            fn on_enter(&mut self, _context: &mut $ctx) -> $crate::sync::Response<$state> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    $($state::$enter_state => {
+                        let hook: &dyn Fn(&mut $ctx) -> $crate::sync::Response<$state> = &$enter;
+                        hook(_context)
+                    })*
+                    _ => $crate::sync::Response::Handled,
+                }
+            }
+
+            fn on_event(&mut self, _event: &$event, _context: &mut $ctx) -> $crate::sync::Response<$state> {
+                #[allow(unreachable_patterns)]
+                match (&*self, _event) {
+                    $(($state::$from, $event::$ev { .. }) => {
+                        $({
+                            let action: &dyn Fn(&mut $ctx, &$event) = &$action;
+                            action(_context, _event);
+                        })?
+                        $crate::sync::Response::Transition($state::$to)
+                    })*
+                    _ => $crate::sync::Response::Unhandled,
+                }
+            }
+
+            fn on_exit(&mut self, _context: &mut $ctx) {
+                #[allow(unreachable_patterns)]
+                match self {
+                    $($state::$exit_state => {
+                        let hook: &dyn Fn(&mut $ctx) = &$exit;
+                        hook(_context)
+                    })*
+                    _ => {}
+                }
+            }
+        }
+    };
+    (
+        Async $state:ident, $ctx:ty, $event:ident;
+        parents { $($child:ident => $parent:ident),* }
+        final { $($final:ident),* }
+        transitions { $($from:ident + $ev:ident => $to:ident $(/ $action:expr)?),* $(,)? }
+        on_enter { $($enter_state:ident => $enter:expr),* $(,)? }
+        on_exit { $($exit_state:ident => $exit:expr),* $(,)? }
+    ) => {
+        impl $crate::Async::FsmEnum<$state, $ctx, $event> for $state {
+            fn create(enum_value: &$state) -> Box<dyn $crate::Async::Stateful<$state, $ctx, $event> + Send> {
+                Box::new(enum_value.clone())
+            }
+
+            fn parent(enum_value: &$state) -> Option<$state> {
+                #[allow(unreachable_patterns)]
+                match enum_value {
+                    $($state::$child => Some($state::$parent),)*
+                    _ => None,
+                }
+            }
+
+            fn is_final(enum_value: &$state) -> bool {
+                #[allow(unreachable_patterns)]
+                match enum_value {
+                    $($state::$final => true,)*
+                    _ => false,
+                }
+            }
+        }
+
+        #[$crate::__private::async_trait]
+        impl $crate::Async::Stateful<$state, $ctx, $event> for $state {
+            async fn on_enter(&mut self, _context: &mut $ctx) -> $crate::Async::Response<$state> {
+                #[allow(unreachable_patterns)]
+                match self {
+                    $($state::$enter_state => {
+                        let hook: &dyn Fn(&mut $ctx) -> $crate::Async::Response<$state> = &$enter;
+                        hook(_context)
+                    })*
+                    _ => $crate::Async::Response::Handled,
+                }
+            }
+
+            async fn on_event(&mut self, _event: &$event, _context: &mut $ctx) -> $crate::Async::Response<$state> {
+                #[allow(unreachable_patterns)]
+                match (&*self, _event) {
+                    $(($state::$from, $event::$ev { .. }) => {
+                        $({
+                            let action: &dyn Fn(&mut $ctx, &$event) = &$action;
+                            action(_context, _event);
+                        })?
+                        $crate::Async::Response::Transition($state::$to)
+                    })*
+                    _ => $crate::Async::Response::Unhandled,
+                }
+            }
+
+            async fn on_exit(&mut self, _context: &mut $ctx) {
+                #[allow(unreachable_patterns)]
+                match self {
+                    $($state::$exit_state => {
+                        let hook: &dyn Fn(&mut $ctx) = &$exit;
+                        hook(_context)
+                    })*
+                    _ => {}
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,129 @@
+//! Observer hooks for state machine activity.
+//!
+//! Observers registered with `StateMachine::with_observer` are notified, in registration order, around
+//! the processing of every event and every state callback. Durations measure the wrapped callback or
+//! event only, and are only measured when at least one observer is registered. `TracingObserver`
+//! opens a `tracing` span for each event processed and reports the notifications in it as structured
+//! events.
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use tracing::Span;
+
+// Define the TransitionObserver trait; every hook has an empty default so observers only implement what they need
+pub trait TransitionObserver<S, E> {
+    // Called when the machine starts processing an event in `state`, including replayed and posted events
+    fn before_dispatch(&mut self, _state: &S, _event: &E) {}
+
+    // Called once the event was processed, whatever the outcome, with the state the machine is now in
+    fn after_dispatch(&mut self, _state: &S, _event: &E, _elapsed: Duration) {}
+
+    // Called before on_exit runs for a state being left
+    fn before_exit(&mut self, _state: &S) {}
+
+    // Called after on_exit returned
+    fn after_exit(&mut self, _state: &S, _elapsed: Duration) {}
+
+    // Called after on_enter returned without an error
+    fn after_enter(&mut self, _state: &S, _elapsed: Duration) {}
+
+    // Called after a state's on_event returned
+    fn after_event(&mut self, _state: &S, _event: &E, _elapsed: Duration) {}
+
+    // Called when a callback of `state` (or the global handler, while in `state`) returned Response::Error
+    fn on_error(&mut self, _state: &S, _error: &str) {}
+
+    // Called when an event was processed without error and the machine stayed in the same state
+    fn on_no_transition(&mut self, _state: &S, _event: &E) {}
+}
+
+// Define the TracingObserver struct, which opens a span for each event processed and emits a tracing
+// event for every notification. The span is not entered, since an async callback may be suspended
+// while it runs: the notifications are attached to it as their explicit parent instead.
+#[derive(Debug, Default)]
+pub struct TracingObserver {
+    // The spans of the events being processed, innermost last
+    spans: Vec<Span>,
+}
+
+impl TracingObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Define a helper returning the span of the event being processed, or the current span outside of one
+    fn parent(&self) -> Span {
+        self.spans.last().cloned().unwrap_or_else(Span::current)
+    }
+}
+
+impl<S: Debug, E: Debug> TransitionObserver<S, E> for TracingObserver {
+    fn before_dispatch(&mut self, state: &S, event: &E) {
+        let span = tracing::debug_span!(
+            "dispatch",
+            state = ?state,
+            event = ?event,
+            to = tracing::field::Empty,
+            elapsed_us = tracing::field::Empty,
+        );
+        self.spans.push(span);
+    }
+
+    fn after_dispatch(&mut self, state: &S, _event: &E, elapsed: Duration) {
+        if let Some(span) = self.spans.pop() {
+            span.record("to", tracing::field::debug(state));
+            span.record("elapsed_us", elapsed.as_micros() as u64);
+        }
+    }
+
+    fn before_exit(&mut self, state: &S) {
+        tracing::trace!(parent: &self.parent(), state = ?state, "exiting state");
+    }
+
+    fn after_exit(&mut self, state: &S, elapsed: Duration) {
+        tracing::debug!(
+            parent: &self.parent(),
+            state = ?state,
+            elapsed_us = elapsed.as_micros() as u64,
+            "exited state"
+        );
+    }
+
+    fn after_enter(&mut self, state: &S, elapsed: Duration) {
+        tracing::debug!(
+            parent: &self.parent(),
+            state = ?state,
+            elapsed_us = elapsed.as_micros() as u64,
+            "entered state"
+        );
+    }
+
+    fn after_event(&mut self, state: &S, event: &E, elapsed: Duration) {
+        tracing::debug!(
+            parent: &self.parent(),
+            state = ?state,
+            event = ?event,
+            elapsed_us = elapsed.as_micros() as u64,
+            "handled event"
+        );
+    }
+
+    fn on_error(&mut self, state: &S, error: &str) {
+        tracing::warn!(
+            parent: &self.parent(),
+            state = ?state,
+            error = error,
+            "state returned an error"
+        );
+    }
+
+    fn on_no_transition(&mut self, state: &S, event: &E) {
+        tracing::trace!(
+            parent: &self.parent(),
+            state = ?state,
+            event = ?event,
+            "event caused no transition"
+        );
+    }
+}
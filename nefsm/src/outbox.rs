@@ -0,0 +1,54 @@
+//! Events raised from inside the state machine.
+//!
+//! State callbacks only receive the context, so a context that wants to raise follow-up events holds
+//! an `Outbox` and implements `HasOutbox`. Once `StateMachine::with_event_queue` is enabled, events
+//! posted during `init` or `process_event` are processed after the current event has run to
+//! completion, in FIFO order, before the call returns. A posted event that fails does not stop the
+//! others, and the call returns the first error. The number of posted events processed per call is
+//! capped to catch events that keep posting each other.
+
+use std::collections::VecDeque;
+
+// Define the Outbox struct, which queues the events posted by state callbacks
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outbox<E> {
+    events: VecDeque<E>,
+}
+
+impl<E> Outbox<E> {
+    pub fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+        }
+    }
+
+    // Define a method to post an event, processed once the current event has run to completion
+    pub fn post(&mut self, event: E) {
+        self.events.push_back(event);
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    // Define a method to take every posted event, oldest first
+    pub fn take(&mut self) -> VecDeque<E> {
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl<E> Default for Outbox<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Define the HasOutbox trait, implemented by contexts that let states post events
+pub trait HasOutbox<E> {
+    fn outbox(&mut self) -> &mut Outbox<E>;
+}
@@ -0,0 +1,14 @@
+//! The `Response` returned by state callbacks.
+//!
+//! The same type is used by `sync`, `Async`, `fixed` and `static_dispatch` states; the `sync` and `Async`
+//! modules name it with `String` as the default error. It only needs `core`, so it remains available
+//! without the `std` feature.
+
+// Define the Response enum, which is used to handle state transitions
+pub enum Response<S, Err = &'static str> {
+    Handled,
+    // The state does not handle the event, which is passed on to its parent state
+    Unhandled,
+    Error(Err),
+    Transition(S),
+}
@@ -0,0 +1,16 @@
+//! Serializable snapshots of a running `StateMachine`, available with the `serde` feature.
+//!
+//! `StateMachine::snapshot` captures the current state, a clone of the context, and the data returned
+//! by `Stateful::save_data` for every cached state object that has any. `StateMachine::restore` builds
+//! a machine back from a snapshot without running any `on_enter`, handing each state its saved data
+//! through `Stateful::restore_data`.
+
+use serde::{Deserialize, Serialize};
+
+// Define the Snapshot struct, which holds everything needed to resume a machine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot<S, CTX> {
+    pub current_state: S,
+    pub context: CTX,
+    pub state_data: Vec<(S, Vec<u8>)>,
+}
@@ -0,0 +1,129 @@
+//! A statically dispatched state machine for hot paths.
+//!
+//! `sync::StateMachine` keeps one boxed `Stateful` object per state in a `HashMap`, so processing an
+//! event hashes the current state, goes through a virtual call, and entering a state for the first
+//! time allocates. `StaticStateMachine` instead holds a single value of a state enum implementing
+//! `StateEnum`: each variant carries the data of its state, the callbacks are a `match` on `self`,
+//! and a transition simply replaces the value with the one returned in `Response::Transition`.
+//! Processing an event never hashes, boxes or allocates, except to build an error.
+//!
+//! The trade-off is a smaller feature set: states are flat (no parents), there is no global handler,
+//! transition table, history, observers or deferred events, and a transition always exits and enters,
+//! even when the new value is the same variant as the current one.
+//!
+//! This module and `Response` (in the `response` module) only need `core`, so they are what remains of
+//! the crate when the `std` feature is disabled, for embedded targets.
+
+use core::marker::PhantomData;
+
+use crate::error::{Error, ErrorKind};
+use crate::response::Response;
+
+// Define the StateEnum trait, implemented by a state enum whose variants hold their own data
+pub trait StateEnum<CTX, E, Err = &'static str>: Sized {
+    fn on_enter(&mut self, context: &mut CTX) -> Response<Self, Err>;
+    fn on_event(&mut self, event: &E, context: &mut CTX) -> Response<Self, Err>;
+    fn on_exit(&mut self, context: &mut CTX);
+}
+
+// Define the StaticStateMachine struct, which owns the current state value and the context
+pub struct StaticStateMachine<S, CTX, E, Err = &'static str> {
+    current_state: Option<S>,
+    context: CTX,
+    max_enter_chain: usize,
+    _event: PhantomData<fn(&E) -> Err>,
+}
+
+impl<S: StateEnum<CTX, E, Err>, CTX, E, Err> StaticStateMachine<S, CTX, E, Err> {
+    // Define a constructor for the StaticStateMachine struct
+    pub fn new(context: CTX) -> Self {
+        Self {
+            current_state: None,
+            context,
+            max_enter_chain: 64,
+            _event: PhantomData,
+        }
+    }
+
+    // Define a method to bound how many times in a row on_enter may request a transition (64 by default)
+    pub fn with_max_enter_chain(mut self, max_enter_chain: usize) -> Self {
+        self.max_enter_chain = max_enter_chain;
+        self
+    }
+
+    // Define a method to get the current state
+    pub fn get_current_state(&self) -> Option<&S> {
+        self.current_state.as_ref()
+    }
+
+    // Define a method to get the context
+    pub fn get_context(&self) -> &CTX {
+        &self.context
+    }
+
+    // Define a method to change the context from outside the machine
+    pub fn get_context_mut(&mut self) -> &mut CTX {
+        &mut self.context
+    }
+
+    // Define a method to take the context and the current state out of the machine
+    pub fn into_parts(self) -> (CTX, Option<S>) {
+        (self.context, self.current_state)
+    }
+
+    // Define a method to initialize the state machine; does nothing if it is already initialized
+    pub fn init(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+        if self.current_state.is_none() {
+            self.enter(initial_state)?;
+        }
+        Ok(())
+    }
+
+    // Define a method to exit the current state and forget it, so that the machine can be initialized again
+    pub fn reset(&mut self) {
+        if let Some(mut state) = self.current_state.take() {
+            state.on_exit(&mut self.context);
+        }
+    }
+
+    // Define a method to reset the machine and initialize it again in `initial_state`
+    pub fn reinit(&mut self, initial_state: S) -> Result<(), Error<S, Err>> {
+        self.reset();
+        self.init(initial_state)
+    }
+
+    // Define a method to process events and transition between states
+    pub fn process_event(&mut self, event: &E) -> Result<(), Error<S, Err>> {
+        let state = match &mut self.current_state {
+            Some(state) => state,
+            None => return Err(Error::new(ErrorKind::StateMachineNotInitialized)),
+        };
+        match state.on_event(event, &mut self.context) {
+            Response::Handled | Response::Unhandled => Ok(()),
+            Response::Error(e) => Err(Error::new(ErrorKind::InvalidEvent(e))),
+            Response::Transition(next_state) => {
+                state.on_exit(&mut self.context);
+                self.enter(next_state)
+            }
+        }
+    }
+
+    // Define a method to enter a state, following any transition requested by on_enter. If on_enter
+    // fails, the previous state stays current, as in `sync::StateMachine`.
+    fn enter(&mut self, mut state: S) -> Result<(), Error<S, Err>> {
+        for _ in 0..=self.max_enter_chain {
+            match state.on_enter(&mut self.context) {
+                Response::Handled | Response::Unhandled => {
+                    self.current_state = Some(state);
+                    return Ok(());
+                }
+                Response::Error(e) => return Err(Error::new(ErrorKind::StateInvalid(e))),
+                Response::Transition(next_state) => state = next_state,
+            }
+        }
+        Err(Error::new(ErrorKind::EnterChainLimitExceeded {
+            last: state,
+            length: self.max_enter_chain + 2,
+        }))
+    }
+}
@@ -0,0 +1,141 @@
+//! Declarative transition tables.
+//!
+//! A `TransitionTable` lists the (state, event) edges of a machine, each optionally carrying a guard
+//! predicate and an action. When a table is attached to a `StateMachine`, `process_event` consults it
+//! before the current state's `on_event`: the first edge whose guard passes has its action run and is
+//! then taken, before any `on_exit` is called. If edges match but every guard rejects the event, the
+//! machine reports `ErrorKind::GuardRejected` and stays where it is.
+
+use std::fmt::Debug;
+use std::mem::{discriminant, Discriminant};
+
+// Define the guard and action types carried by a transition
+pub type Guard<CTX, E> = Box<dyn Fn(&CTX, &E) -> bool + Send>;
+pub type Action<CTX, E> = Box<dyn Fn(&mut CTX, &E) + Send>;
+
+// Define the Transition struct, which describes a single edge of the state graph
+pub struct Transition<S, CTX, E> {
+    from: S,
+    event: Discriminant<E>,
+    event_name: String,
+    to: S,
+    guard: Option<Guard<CTX, E>>,
+    action: Option<Action<CTX, E>>,
+}
+
+impl<S: PartialEq, CTX, E: Debug> Transition<S, CTX, E> {
+    // Define a constructor for an edge from `from` to `to`. Events are matched by variant,
+    // so any value of the variant can be used as `event`, whatever its payload.
+    pub fn new(from: S, event: &E, to: S) -> Self {
+        Self {
+            from,
+            event: discriminant(event),
+            event_name: event_name(event),
+            to,
+            guard: None,
+            action: None,
+        }
+    }
+
+    // Define a method to set the guard predicate, which must return true for the edge to be taken
+    pub fn guard(mut self, guard: impl Fn(&CTX, &E) -> bool + Send + 'static) -> Self {
+        self.guard = Some(Box::new(guard));
+        self
+    }
+
+    // Define a method to set the action, which runs once the guard has passed and before on_exit
+    pub fn action(mut self, action: impl Fn(&mut CTX, &E) + Send + 'static) -> Self {
+        self.action = Some(Box::new(action));
+        self
+    }
+
+    pub fn get_from(&self) -> &S {
+        &self.from
+    }
+
+    pub fn get_to(&self) -> &S {
+        &self.to
+    }
+
+    // Define a method to get the event variant name, as printed by Debug without any payload
+    pub fn get_event_name(&self) -> &str {
+        &self.event_name
+    }
+
+    pub fn has_guard(&self) -> bool {
+        self.guard.is_some()
+    }
+
+    // Define a method to check whether this edge leaves `state` on `event`
+    pub fn matches(&self, state: &S, event: &E) -> bool {
+        self.from == *state && self.event == discriminant(event)
+    }
+
+    // Define a method to evaluate the guard; edges without a guard are always allowed
+    pub fn is_allowed(&self, context: &CTX, event: &E) -> bool {
+        match &self.guard {
+            Some(guard) => guard(context, event),
+            None => true,
+        }
+    }
+
+    // Define a method to run the action, if any
+    pub fn run_action(&self, context: &mut CTX, event: &E) {
+        if let Some(action) = &self.action {
+            action(context, event);
+        }
+    }
+}
+
+// Define the TransitionTable struct, which holds every declared edge in declaration order
+pub struct TransitionTable<S, CTX, E> {
+    transitions: Vec<Transition<S, CTX, E>>,
+}
+
+impl<S: PartialEq, CTX, E: Debug> TransitionTable<S, CTX, E> {
+    pub fn new() -> Self {
+        Self {
+            transitions: Vec::new(),
+        }
+    }
+
+    // Define a method to add an edge to the table
+    pub fn add_transition(mut self, transition: Transition<S, CTX, E>) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    pub fn get_transitions(&self) -> &[Transition<S, CTX, E>] {
+        &self.transitions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transitions.is_empty()
+    }
+
+    // Define a method to iterate over the edges leaving `state` on `event`, in declaration order
+    pub fn find<'a>(
+        &'a self,
+        state: &'a S,
+        event: &'a E,
+    ) -> impl Iterator<Item = &'a Transition<S, CTX, E>> + 'a {
+        self.transitions
+            .iter()
+            .filter(move |t| t.matches(state, event))
+    }
+}
+
+impl<S: PartialEq, CTX, E: Debug> Default for TransitionTable<S, CTX, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Define a helper to get the name of an event variant from its Debug output
+pub(crate) fn event_name<E: Debug>(event: &E) -> String {
+    let name = format!("{:?}", event);
+    match name.find(['(', '{', ' ']) {
+        Some(end) => name[..end].to_string(),
+        None => name,
+    }
+}
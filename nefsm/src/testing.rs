@@ -0,0 +1,273 @@
+//! Scenario-based unit testing of state machines, behind the `testing` feature.
+//!
+//! A `Scenario` starts a machine in an initial state, then runs a list of steps in order: events to
+//! process, and expectations on the current state, on the error returned by the last event, or on the
+//! context. Any failed expectation, or an error no `expect_error` step accounts for, panics with the
+//! step that failed and a trace of every callback the machine ran so far. `run` drives a
+//! `sync::StateMachine` and `run_async` an `Async::StateMachine`; both hand the machine back so the
+//! test can go on checking it.
+
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::{Error, ErrorKind};
+use crate::observer::TransitionObserver;
+use crate::{sync, Async};
+
+// Define the ErrorMatcher type, which is a predicate on the error an expect_error step checks
+type ErrorMatcher<S, Err> = Box<dyn Fn(&ErrorKind<S, Err>) -> bool>;
+
+// Define the Step enum, which is a single action or expectation of a scenario
+enum Step<S, CTX, E, Err> {
+    Event(E),
+    ExpectState(S),
+    ExpectError(ErrorMatcher<S, Err>),
+    ExpectContext(String, Box<dyn Fn(&CTX) -> bool>),
+}
+
+// Define the Scenario struct, which lists the steps to run against a machine
+pub struct Scenario<S, CTX, E, Err = String> {
+    initial_state: S,
+    steps: Vec<Step<S, CTX, E, Err>>,
+}
+
+impl<S, CTX, E, Err> Scenario<S, CTX, E, Err>
+where
+    S: Hash + PartialEq + Eq + Clone + Debug,
+    E: Debug,
+    Err: Debug + Display,
+{
+    // Define a constructor for a scenario initializing the machine in `initial_state`
+    pub fn new(initial_state: S) -> Self {
+        Self {
+            initial_state,
+            steps: Vec::new(),
+        }
+    }
+
+    // Define a method to process an event
+    pub fn event(mut self, event: E) -> Self {
+        self.steps.push(Step::Event(event));
+        self
+    }
+
+    // Define a method to expect the machine to be in `state`
+    pub fn expect_state(mut self, state: S) -> Self {
+        self.steps.push(Step::ExpectState(state));
+        self
+    }
+
+    // Define a method to expect the last event, or the initialization, to have failed with a matching error
+    pub fn expect_error(mut self, matches: impl Fn(&ErrorKind<S, Err>) -> bool + 'static) -> Self {
+        self.steps.push(Step::ExpectError(Box::new(matches)));
+        self
+    }
+
+    // Define a method to expect the context to satisfy a predicate, described in failure messages
+    pub fn expect_context(
+        mut self,
+        description: &str,
+        predicate: impl Fn(&CTX) -> bool + 'static,
+    ) -> Self {
+        self.steps.push(Step::ExpectContext(
+            description.to_string(),
+            Box::new(predicate),
+        ));
+        self
+    }
+
+    // Define a method to run the scenario against a sync machine, which must not be initialized yet
+    pub fn run(
+        self,
+        machine: sync::StateMachine<S, CTX, E, Err>,
+    ) -> sync::StateMachine<S, CTX, E, Err>
+    where
+        S: sync::FsmEnum<S, CTX, E, Err>,
+    {
+        let trace = Trace::default();
+        let mut machine = machine.with_observer(Box::new(trace.clone()));
+        let mut run = Run::new(trace);
+        run.log(format!("init {:?}", self.initial_state));
+        let result = machine.init(self.initial_state);
+        run.record(result, machine.get_current_state());
+        for (index, step) in self.steps.into_iter().enumerate() {
+            match step {
+                Step::Event(event) => {
+                    run.check_no_error(index);
+                    run.log(format!("event {:?}", event));
+                    let result = machine.process_event(&event);
+                    run.record(result, machine.get_current_state());
+                }
+                step => run.check(
+                    index,
+                    step,
+                    machine.get_current_state(),
+                    machine.get_context(),
+                ),
+            }
+        }
+        run.finish();
+        machine
+    }
+
+    // Define a method to run the scenario against an async machine, which must not be initialized yet
+    pub async fn run_async(
+        self,
+        machine: Async::StateMachine<S, CTX, E, Err>,
+    ) -> Async::StateMachine<S, CTX, E, Err>
+    where
+        S: Async::FsmEnum<S, CTX, E, Err>,
+    {
+        let trace = Trace::default();
+        let mut machine = machine.with_observer(Box::new(trace.clone()));
+        let mut run = Run::new(trace);
+        run.log(format!("init {:?}", self.initial_state));
+        let result = machine.init(self.initial_state).await;
+        run.record(result, machine.get_current_state());
+        for (index, step) in self.steps.into_iter().enumerate() {
+            match step {
+                Step::Event(event) => {
+                    run.check_no_error(index);
+                    run.log(format!("event {:?}", event));
+                    let result = machine.process_event(&event).await;
+                    run.record(result, machine.get_current_state());
+                }
+                step => run.check(
+                    index,
+                    step,
+                    machine.get_current_state(),
+                    machine.get_context(),
+                ),
+            }
+        }
+        run.finish();
+        machine
+    }
+}
+
+// Define the Trace struct, an observer writing every callback to a log shared with the scenario
+#[derive(Clone, Default)]
+struct Trace {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Trace {
+    fn push(&self, line: String) {
+        self.lines.lock().unwrap().push(line);
+    }
+}
+
+impl<S: Debug, E: Debug> TransitionObserver<S, E> for Trace {
+    fn after_exit(&mut self, state: &S, _elapsed: Duration) {
+        self.push(format!("  exit {:?}", state));
+    }
+
+    fn after_enter(&mut self, state: &S, _elapsed: Duration) {
+        self.push(format!("  enter {:?}", state));
+    }
+
+    fn after_event(&mut self, state: &S, event: &E, _elapsed: Duration) {
+        self.push(format!("  {:?} handled {:?}", state, event));
+    }
+
+    fn on_error(&mut self, state: &S, error: &str) {
+        self.push(format!("  {:?} returned an error: {}", state, error));
+    }
+
+    fn on_no_transition(&mut self, state: &S, event: &E) {
+        self.push(format!("  {:?} stayed after {:?}", state, event));
+    }
+}
+
+// Define the Run struct, which is the progress of a scenario: its trace and the error left to check
+struct Run<S, Err> {
+    trace: Trace,
+    error: Option<Error<S, Err>>,
+}
+
+impl<S: PartialEq + Debug, Err: Display> Run<S, Err> {
+    fn new(trace: Trace) -> Self {
+        Self { trace, error: None }
+    }
+
+    // Define a helper adding a line to the trace
+    fn log(&self, line: String) {
+        self.trace.push(line);
+    }
+
+    // Define a helper recording the outcome of init or of an event
+    fn record(&mut self, result: Result<(), Error<S, Err>>, state: Option<&S>) {
+        match result {
+            Ok(()) => self.log(format!("  -> now in {:?}", state)),
+            Err(e) => {
+                self.log(format!("  -> error: {}", e));
+                self.error = Some(e);
+            }
+        }
+    }
+
+    // Define a helper checking an expectation step against the machine
+    fn check<CTX, E>(
+        &mut self,
+        index: usize,
+        step: Step<S, CTX, E, Err>,
+        state: Option<&S>,
+        context: &CTX,
+    ) {
+        match step {
+            Step::Event(_) => unreachable!("events are processed by the caller"),
+            Step::ExpectError(matches) => match self.error.take() {
+                Some(e) if matches(e.kind()) => {}
+                Some(e) => self.fail(index, format!("the error does not match: {}", e)),
+                None => self.fail(
+                    index,
+                    "expected an error, but the last step succeeded".into(),
+                ),
+            },
+            Step::ExpectState(expected) => {
+                self.check_no_error(index);
+                if state != Some(&expected) {
+                    self.fail(
+                        index,
+                        format!(
+                            "expected state {:?}, but the machine is in {:?}",
+                            expected, state
+                        ),
+                    );
+                }
+            }
+            Step::ExpectContext(description, predicate) => {
+                self.check_no_error(index);
+                if !predicate(context) {
+                    self.fail(index, format!("expected context: {}", description));
+                }
+            }
+        }
+    }
+
+    // Define a helper failing if the last step returned an error no expect_error step checked
+    fn check_no_error(&mut self, index: usize) {
+        if let Some(e) = self.error.take() {
+            self.fail(index, format!("the previous step failed: {}", e));
+        }
+    }
+
+    // Define a helper checking that the scenario did not end on an unexpected error
+    fn finish(&mut self) {
+        if let Some(e) = self.error.take() {
+            self.fail_with(format!("scenario ended with an unexpected error: {}", e));
+        }
+    }
+
+    // Define a helper panicking with the failed step (numbered from 1) and the trace
+    fn fail(&self, index: usize, message: String) {
+        self.fail_with(format!("step {} failed: {}", index + 1, message));
+    }
+
+    fn fail_with(&self, message: String) {
+        let lines = self.trace.lines.lock().unwrap().join("\n");
+        panic!("{}\ntrace:\n{}", message, lines);
+    }
+}
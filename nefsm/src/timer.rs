@@ -0,0 +1,319 @@
+//! Timers for `Async::StateMachine`.
+//!
+//! A state schedules timer events from `Async::Stateful::schedule_timers`, which the machine calls
+//! right after the state's `on_enter` succeeds. The timers belong to that state and are cancelled as
+//! soon as it is exited. Due timers are delivered through `StateMachine::fire_due_timers`, and
+//! `StateMachine::next_timer` returns a future to wait on alongside other event sources.
+//!
+//! Time is read from a `Clock`: `SystemClock` works with any runtime, `TokioClock` (with the `tokio`
+//! feature) follows tokio's time driver, and `ManualClock` only moves when told to, for tests.
+//! `SystemClock` completes its sleeps from a single background thread, shared by every machine and
+//! started on first use.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Define the Clock trait, which abstracts the passing of time away from any runtime
+pub trait Clock: Send + Sync {
+    // Define a method to get the time elapsed since an arbitrary, fixed origin
+    fn now(&self) -> Duration;
+
+    // Define a method to get a future resolving once `duration` has passed on this clock
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+// Define the Timers struct, which collects the timer events scheduled by a state
+pub struct Timers<E> {
+    scheduled: Vec<(Duration, E)>,
+}
+
+impl<E> Timers<E> {
+    pub(crate) fn new() -> Self {
+        Self {
+            scheduled: Vec::new(),
+        }
+    }
+
+    // Define a method to deliver `event` after `delay`, unless the state is exited first
+    pub fn after(&mut self, delay: Duration, event: E) {
+        self.scheduled.push((delay, event));
+    }
+
+    pub(crate) fn into_scheduled(self) -> Vec<(Duration, E)> {
+        self.scheduled
+    }
+}
+
+// Define the SystemClock struct, which uses the monotonic system clock and the shared timer thread
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(ThreadSleep {
+            deadline: Instant::now() + duration,
+            key: None,
+        })
+    }
+}
+
+// Define the TimerQueue struct, which holds the wakers of the pending sleeps by deadline
+struct TimerQueue {
+    wakers: BTreeMap<(Instant, u64), Waker>,
+    next_id: u64,
+    started: bool,
+}
+
+// Define the TimerThread struct, the state shared with the thread that wakes the sleeps when they are due
+struct TimerThread {
+    queue: Mutex<TimerQueue>,
+    changed: Condvar,
+}
+
+static TIMER_THREAD: TimerThread = TimerThread {
+    queue: Mutex::new(TimerQueue {
+        wakers: BTreeMap::new(),
+        next_id: 0,
+        started: false,
+    }),
+    changed: Condvar::new(),
+};
+
+impl TimerThread {
+    // Define a method to register or update the waker of a sleep, starting the thread on first use.
+    // Returns the key the waker is registered under.
+    fn register(
+        &'static self,
+        deadline: Instant,
+        key: Option<(Instant, u64)>,
+        waker: &Waker,
+    ) -> (Instant, u64) {
+        let mut queue = self.queue.lock().unwrap();
+        if !queue.started {
+            queue.started = true;
+            thread::spawn(move || self.run());
+        }
+        let key = key.unwrap_or_else(|| {
+            queue.next_id += 1;
+            (deadline, queue.next_id)
+        });
+        let earliest = queue.wakers.keys().next().is_none_or(|first| key < *first);
+        queue.wakers.insert(key, waker.clone());
+        if earliest {
+            self.changed.notify_one();
+        }
+        key
+    }
+
+    // Define a method to forget a sleep that completed or was dropped
+    fn cancel(&self, key: &(Instant, u64)) {
+        self.queue.lock().unwrap().wakers.remove(key);
+    }
+
+    // Define the body of the thread, which wakes the due sleeps then waits for the next deadline
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let pending = queue.wakers.split_off(&(now, u64::MAX));
+            let due = std::mem::replace(&mut queue.wakers, pending);
+            if !due.is_empty() {
+                // Wake the tasks without the lock, as they may poll their sleep right away
+                drop(queue);
+                due.into_values().for_each(Waker::wake);
+                queue = self.queue.lock().unwrap();
+                continue;
+            }
+            queue = match queue.wakers.keys().next() {
+                Some((deadline, _)) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.changed.wait_timeout(queue, timeout).unwrap().0
+                }
+                None => self.changed.wait(queue).unwrap(),
+            };
+        }
+    }
+}
+
+// Define the ThreadSleep future, woken by the shared timer thread once its deadline has passed
+struct ThreadSleep {
+    deadline: Instant,
+    key: Option<(Instant, u64)>,
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            if let Some(key) = self.key.take() {
+                TIMER_THREAD.cancel(&key);
+            }
+            return Poll::Ready(());
+        }
+        let key = TIMER_THREAD.register(self.deadline, self.key, cx.waker());
+        self.key = Some(key);
+        Poll::Pending
+    }
+}
+
+impl Drop for ThreadSleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            TIMER_THREAD.cancel(&key);
+        }
+    }
+}
+
+// Define the ManualClock struct, a clock for tests which only advances when asked to.
+// Clones share the same time. A sleep completes once the clock has been advanced to its deadline.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    time: Arc<Mutex<ManualTime>>,
+}
+
+// Define the ManualTime struct, which holds the time of a manual clock and the wakers of its pending sleeps
+#[derive(Debug, Default)]
+struct ManualTime {
+    now: Duration,
+    sleeps: HashMap<u64, (Duration, Waker)>,
+    next_id: u64,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Define a method to move the clock forward, waking the sleeps that are now due
+    pub fn advance(&self, duration: Duration) {
+        let mut time = self.time.lock().unwrap();
+        time.now += duration;
+        let now = time.now;
+        let mut wakers = Vec::new();
+        time.sleeps.retain(|_, (deadline, waker)| {
+            let due = *deadline <= now;
+            if due {
+                wakers.push(waker.clone());
+            }
+            !due
+        });
+        // Wake the tasks without the lock, as they may poll their sleep right away
+        drop(time);
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.time.lock().unwrap().now
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(ManualSleep {
+            time: self.time.clone(),
+            deadline: self.now() + duration,
+            id: None,
+        })
+    }
+}
+
+// Define the ManualSleep future, woken by `ManualClock::advance` once its deadline is reached
+struct ManualSleep {
+    time: Arc<Mutex<ManualTime>>,
+    deadline: Duration,
+    id: Option<u64>,
+}
+
+impl Future for ManualSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let deadline = self.deadline;
+        let id = self.id;
+        let mut time = self.time.lock().unwrap();
+        if time.now >= deadline {
+            if let Some(id) = id {
+                time.sleeps.remove(&id);
+            }
+            return Poll::Ready(());
+        }
+        let id = id.unwrap_or_else(|| {
+            time.next_id += 1;
+            time.next_id
+        });
+        time.sleeps.insert(id, (deadline, cx.waker().clone()));
+        drop(time);
+        self.id = Some(id);
+        Poll::Pending
+    }
+}
+
+impl Drop for ManualSleep {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.time.lock().unwrap().sleeps.remove(&id);
+        }
+    }
+}
+
+// Define the TokioClock struct, which follows tokio's time driver, including paused time in tests
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy)]
+pub struct TokioClock {
+    origin: tokio::time::Instant,
+}
+
+#[cfg(feature = "tokio")]
+impl TokioClock {
+    pub fn new() -> Self {
+        Self {
+            origin: tokio::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Clock for TokioClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
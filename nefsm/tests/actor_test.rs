@@ -0,0 +1,213 @@
+#![cfg(feature = "tokio")]
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use nefsm::actor::StateMachineHandle;
+    use nefsm::timer::{Clock, ManualClock, Sleep, Timers, TokioClock};
+    use nefsm::Async::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CounterState {
+        Counting,
+        Done,
+    }
+
+    #[derive(Debug)]
+    enum CounterEvent {
+        Add(u32),
+        Finish,
+        Expire,
+    }
+
+    impl FsmEnum<CounterState, u32, CounterEvent> for CounterState {
+        fn create(
+            enum_value: &CounterState,
+        ) -> Box<dyn Stateful<CounterState, u32, CounterEvent> + Send> {
+            Box::new(Counter {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Counter {
+        id: CounterState,
+    }
+
+    #[async_trait]
+    impl Stateful<CounterState, u32, CounterEvent> for Counter {
+        async fn on_enter(&mut self, _context: &mut u32) -> Response<CounterState> {
+            Response::Handled
+        }
+
+        async fn on_event(
+            &mut self,
+            event: &CounterEvent,
+            context: &mut u32,
+        ) -> Response<CounterState> {
+            match (&self.id, event) {
+                (CounterState::Counting, CounterEvent::Add(n)) => {
+                    *context += n;
+                    Response::Handled
+                }
+                (CounterState::Counting, CounterEvent::Finish | CounterEvent::Expire) => {
+                    Response::Transition(CounterState::Done)
+                }
+                _ => Response::Error("counter is done".to_string()),
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut u32) {}
+
+        fn schedule_timers(&mut self, _context: &u32, timers: &mut Timers<CounterEvent>) {
+            if self.id == CounterState::Counting {
+                timers.after(Duration::from_millis(20), CounterEvent::Expire);
+            }
+        }
+    }
+
+    async fn spawn_counter() -> (
+        StateMachineHandle<CounterState, CounterEvent>,
+        tokio::task::JoinHandle<StateMachine<CounterState, u32, CounterEvent>>,
+    ) {
+        let mut sm = StateMachine::new(0, None).with_clock(Arc::new(TokioClock::new()));
+        sm.init(CounterState::Counting).await.unwrap();
+        StateMachineHandle::spawn(sm, 16)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_handle_shared_between_tasks() {
+        let (handle, task) = spawn_counter().await;
+
+        let senders: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                tokio::spawn(async move {
+                    for _ in 0..5 {
+                        handle.send(CounterEvent::Add(1)).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for sender in senders {
+            sender.await.unwrap();
+        }
+
+        assert_eq!(
+            handle.current_state().await.unwrap(),
+            Some(CounterState::Counting)
+        );
+        assert_eq!(
+            handle.call(CounterEvent::Finish).await.unwrap(),
+            CounterState::Done
+        );
+        assert!(matches!(
+            handle.call(CounterEvent::Add(1)).await.map_err(Error::into_kind),
+            Err(ErrorKind::InvalidEvent(_))
+        ));
+
+        handle.shutdown().await.unwrap();
+        let sm = task.await.unwrap();
+        assert_eq!(*sm.get_context(), 20);
+        assert!(matches!(
+            handle.send(CounterEvent::Finish).await.map_err(Error::into_kind),
+            Err(ErrorKind::InternalError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handle_fires_timers() {
+        let (handle, task) = spawn_counter().await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            handle.current_state().await.unwrap(),
+            Some(CounterState::Done)
+        );
+
+        // Dropping the last handle stops the task
+        drop(handle);
+        assert_eq!(
+            *task.await.unwrap().get_current_state().unwrap(),
+            CounterState::Done
+        );
+    }
+
+    // Define a clock counting the sleeps it was asked for
+    struct CountingClock {
+        inner: TokioClock,
+        sleeps: Arc<AtomicUsize>,
+    }
+
+    impl Clock for CountingClock {
+        fn now(&self) -> Duration {
+            self.inner.now()
+        }
+
+        fn sleep(&self, duration: Duration) -> Sleep {
+            self.sleeps.fetch_add(1, Ordering::Relaxed);
+            self.inner.sleep(duration)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_keeps_the_timer_sleep() {
+        let sleeps = Arc::new(AtomicUsize::new(0));
+        let clock = CountingClock {
+            inner: TokioClock::new(),
+            sleeps: sleeps.clone(),
+        };
+        let mut sm = StateMachine::new(0, None).with_clock(Arc::new(clock));
+        sm.init(CounterState::Counting).await.unwrap();
+        let (handle, _task) = StateMachineHandle::spawn(sm, 16);
+
+        // The pending timer does not change, so the task keeps waiting on the same sleep
+        for _ in 0..200 {
+            handle.call(CounterEvent::Add(1)).await.unwrap();
+        }
+        assert_eq!(sleeps.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_waits_for_a_manual_clock() {
+        let clock = ManualClock::new();
+        let mut sm = StateMachine::new(0, None).with_clock(Arc::new(clock.clone()));
+        sm.init(CounterState::Counting).await.unwrap();
+        let (handle, _task) = StateMachineHandle::spawn(sm, 16);
+
+        // The timer only fires once the clock is advanced to its deadline
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        clock.advance(Duration::from_millis(19));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            handle.current_state().await.unwrap(),
+            Some(CounterState::Counting)
+        );
+        assert_eq!(clock.now(), Duration::from_millis(19));
+
+        clock.advance(Duration::from_millis(1));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            handle.current_state().await.unwrap(),
+            Some(CounterState::Done)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_handle_uses_tokio_time_by_default() {
+        let mut sm = StateMachine::new(0, None);
+        sm.init(CounterState::Counting).await.unwrap();
+        let (handle, _task) = StateMachineHandle::spawn(sm, 16);
+
+        // With tokio's clock paused, the timer fires as soon as the runtime is idle, without waiting
+        let started = Instant::now();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            handle.current_state().await.unwrap(),
+            Some(CounterState::Done)
+        );
+        assert!(started.elapsed() < Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::analysis::Analyzer;
+    use nefsm::table::{Transition, TransitionTable};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum OrderState {
+        Cart,
+        Checkout,
+        Payment,
+        Paid,
+        Shipped,
+        Refunded,
+        Archived,
+    }
+
+    #[derive(Debug)]
+    enum OrderEvent {
+        Checkout,
+        Pay(u32),
+        Confirm,
+        Ship,
+        Cancel,
+        Refund,
+    }
+
+    fn parent(state: &OrderState) -> Option<OrderState> {
+        match state {
+            OrderState::Payment => Some(OrderState::Checkout),
+            _ => None,
+        }
+    }
+
+    fn order_table() -> TransitionTable<OrderState, u32, OrderEvent> {
+        TransitionTable::new()
+            .add_transition(Transition::new(
+                OrderState::Cart,
+                &OrderEvent::Checkout,
+                OrderState::Payment,
+            ))
+            .add_transition(
+                Transition::new(OrderState::Payment, &OrderEvent::Pay(0), OrderState::Paid)
+                    .guard(|_, event| matches!(event, OrderEvent::Pay(amount) if *amount > 0)),
+            )
+            // Handled by Payment through its parent
+            .add_transition(Transition::new(
+                OrderState::Checkout,
+                &OrderEvent::Cancel,
+                OrderState::Cart,
+            ))
+            .add_transition(Transition::new(
+                OrderState::Paid,
+                &OrderEvent::Ship,
+                OrderState::Shipped,
+            ))
+    }
+
+    #[test]
+    fn test_clean_table() {
+        let table = order_table();
+        let analysis = Analyzer::new(&table, OrderState::Cart)
+            .with_parents(parent)
+            .with_events(&[
+                OrderEvent::Checkout,
+                OrderEvent::Pay(0),
+                OrderEvent::Ship,
+                OrderEvent::Cancel,
+            ])
+            .with_final_states(&[OrderState::Shipped])
+            .analyze();
+        assert!(analysis.is_clean(), "{}", analysis);
+        assert_eq!(analysis.to_string(), "no problems found\n");
+    }
+
+    #[test]
+    fn test_problems_are_reported() {
+        let table = order_table()
+            // Shadows the guarded edge declared after it
+            .add_transition(Transition::new(
+                OrderState::Paid,
+                &OrderEvent::Refund,
+                OrderState::Refunded,
+            ))
+            .add_transition(
+                Transition::new(OrderState::Paid, &OrderEvent::Refund, OrderState::Cart)
+                    .guard(|attempts: &u32, _| *attempts < 3),
+            );
+        let analysis = Analyzer::new(&table, OrderState::Cart)
+            .with_parents(parent)
+            .with_states(&[
+                OrderState::Cart,
+                OrderState::Checkout,
+                OrderState::Payment,
+                OrderState::Paid,
+                OrderState::Shipped,
+                OrderState::Refunded,
+                OrderState::Archived,
+            ])
+            .with_events(&[OrderEvent::Pay(1), OrderEvent::Confirm])
+            .with_final_states(&[OrderState::Shipped])
+            .analyze();
+
+        assert_eq!(analysis.unreachable_states, vec![OrderState::Archived]);
+        assert_eq!(analysis.dead_end_states, vec![OrderState::Refunded]);
+        assert_eq!(analysis.unhandled_events, vec!["Confirm".to_string()]);
+        assert_eq!(
+            analysis.nondeterministic_edges,
+            vec![(OrderState::Paid, "Refund".to_string())]
+        );
+        assert!(analysis.enter_cycles.is_empty());
+        assert!(!analysis.is_clean());
+        assert!(analysis
+            .to_string()
+            .contains("unreachable state: Archived\n"));
+    }
+
+    #[test]
+    fn test_enter_cycles() {
+        let table = order_table();
+        let analysis = Analyzer::new(&table, OrderState::Cart)
+            .with_parents(parent)
+            .with_final_states(&[OrderState::Shipped])
+            // Paid and Refunded keep redirecting to each other; Shipped redirecting to itself is fine
+            .with_enter_transition(OrderState::Paid, OrderState::Refunded)
+            .with_enter_transition(OrderState::Refunded, OrderState::Paid)
+            .with_enter_transition(OrderState::Shipped, OrderState::Shipped)
+            .analyze();
+        assert_eq!(
+            analysis.enter_cycles,
+            vec![vec![OrderState::Paid, OrderState::Refunded]]
+        );
+        assert!(analysis.dead_end_states.is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::cache::CachePolicy;
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum Room {
+        Hall,
+        Kitchen,
+        Garden,
+        // Only passed through: entering the porch leads straight back to the hall
+        Porch,
+        // Locked: entering the cellar fails
+        Cellar,
+    }
+
+    #[derive(Debug)]
+    enum Walk {
+        To(Room),
+    }
+
+    // Records each entry and each handled event as (room, visits counted by the state object),
+    // and each exit
+    #[derive(Default, Clone)]
+    struct Log {
+        entries: Vec<(Room, u32)>,
+        handled: Vec<(Room, u32)>,
+        exits: Vec<Room>,
+    }
+
+    impl FsmEnum<Room, Log, Walk> for Room {
+        fn create(enum_value: &Room) -> Box<dyn Stateful<Room, Log, Walk> + Send> {
+            Box::new(Visited {
+                id: enum_value.clone(),
+                visits: 0,
+            })
+        }
+    }
+
+    struct Visited {
+        id: Room,
+        visits: u32,
+    }
+
+    impl Stateful<Room, Log, Walk> for Visited {
+        fn on_enter(&mut self, context: &mut Log) -> Response<Room> {
+            self.visits += 1;
+            context.entries.push((self.id.clone(), self.visits));
+            match self.id {
+                Room::Porch => Response::Transition(Room::Hall),
+                Room::Cellar => Response::Error("the cellar is locked".to_string()),
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(&mut self, event: &Walk, context: &mut Log) -> Response<Room> {
+            context.handled.push((self.id.clone(), self.visits));
+            match event {
+                Walk::To(room) => Response::Transition(room.clone()),
+            }
+        }
+
+        fn on_exit(&mut self, context: &mut Log) {
+            context.exits.push(self.id.clone());
+        }
+    }
+
+    fn walk(policy: CachePolicy, rooms: &[Room]) -> Vec<(Room, u32)> {
+        let mut sm = StateMachine::new(Log::default(), None).with_cache_policy(policy);
+        sm.init(Room::Hall).unwrap();
+        for room in rooms {
+            sm.process_event(&Walk::To(room.clone())).unwrap();
+        }
+        sm.into_parts().0.entries
+    }
+
+    #[test]
+    fn test_cache_forever_keeps_state_fields() {
+        let entries = walk(
+            CachePolicy::Forever,
+            &[Room::Kitchen, Room::Hall, Room::Kitchen],
+        );
+        assert_eq!(
+            entries,
+            vec![
+                (Room::Hall, 1),
+                (Room::Kitchen, 1),
+                (Room::Hall, 2),
+                (Room::Kitchen, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recreate_on_entry_resets_state_fields() {
+        let entries = walk(
+            CachePolicy::RecreateOnEntry,
+            &[Room::Kitchen, Room::Hall, Room::Kitchen],
+        );
+        assert_eq!(
+            entries,
+            vec![
+                (Room::Hall, 1),
+                (Room::Kitchen, 1),
+                (Room::Hall, 1),
+                (Room::Kitchen, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lru_drops_least_recently_exited_state() {
+        // With room for two objects, leaving the Hall for the Garden drops the Kitchen's,
+        // which was exited earlier, while the Hall's survives
+        let entries = walk(
+            CachePolicy::Lru(2),
+            &[
+                Room::Kitchen,
+                Room::Hall,
+                Room::Garden,
+                Room::Hall,
+                Room::Kitchen,
+            ],
+        );
+        assert_eq!(
+            entries,
+            vec![
+                (Room::Hall, 1),
+                (Room::Kitchen, 1),
+                (Room::Hall, 2),
+                (Room::Garden, 1),
+                (Room::Hall, 3),
+                (Room::Kitchen, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirecting_state_is_evicted() {
+        // The porch's on_enter redirects to the hall, so it is never exited but must still be dropped
+        let walk_through_porch = [Room::Porch, Room::Kitchen, Room::Porch];
+        assert_eq!(
+            walk(CachePolicy::RecreateOnEntry, &walk_through_porch),
+            vec![
+                (Room::Hall, 1),
+                (Room::Porch, 1),
+                (Room::Hall, 1),
+                (Room::Kitchen, 1),
+                (Room::Porch, 1),
+                (Room::Hall, 1)
+            ]
+        );
+        assert_eq!(
+            walk(CachePolicy::Lru(2), &walk_through_porch),
+            vec![
+                (Room::Hall, 1),
+                (Room::Porch, 1),
+                (Room::Hall, 2),
+                (Room::Kitchen, 1),
+                (Room::Porch, 1),
+                (Room::Hall, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rollback_keeps_the_source_state_object() {
+        // Leaving the hall for the cellar drops the hall's object before the cellar fails to enter;
+        // the rollback must bring it back rather than let the next event recreate it
+        for policy in [CachePolicy::RecreateOnEntry, CachePolicy::Lru(1)] {
+            let mut sm = StateMachine::new(Log::default(), None)
+                .with_cache_policy(policy)
+                .with_transactions();
+            sm.init(Room::Hall).unwrap();
+            assert!(sm.process_event(&Walk::To(Room::Cellar)).is_err());
+            assert_eq!(*sm.get_current_state().unwrap(), Room::Hall);
+
+            sm.process_event(&Walk::To(Room::Kitchen)).unwrap();
+            let log = sm.into_parts().0;
+            assert_eq!(log.handled, vec![(Room::Hall, 1)]);
+            assert_eq!(log.entries, vec![(Room::Hall, 1), (Room::Kitchen, 1)]);
+            assert_eq!(log.exits, vec![Room::Hall]);
+        }
+    }
+
+    #[test]
+    fn test_reset_and_reinit() {
+        let mut sm = StateMachine::new(Log::default(), None);
+        sm.init(Room::Hall).unwrap();
+        sm.process_event(&Walk::To(Room::Kitchen)).unwrap();
+
+        sm.reset();
+        assert!(sm.get_current_state().is_none());
+        assert_eq!(sm.get_context().exits, vec![Room::Hall, Room::Kitchen]);
+        assert!(matches!(
+            sm.process_event(&Walk::To(Room::Hall))
+                .map_err(Error::into_kind),
+            Err(ErrorKind::StateMachineNotInitialized)
+        ));
+
+        // Resetting a machine that is not initialized does nothing
+        sm.reset();
+        assert_eq!(sm.get_context().exits.len(), 2);
+
+        sm.reinit(Room::Garden).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), Room::Garden);
+        sm.reinit(Room::Kitchen).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), Room::Kitchen);
+        assert_eq!(sm.get_context().exits.last(), Some(&Room::Garden));
+        assert_eq!(sm.get_context().entries.last(), Some(&(Room::Kitchen, 2)));
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::cache::CachePolicy;
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum Room {
+        Hall,
+        Kitchen,
+    }
+
+    #[derive(Debug)]
+    enum Walk {
+        To(Room),
+    }
+
+    #[derive(Default)]
+    struct Log {
+        entries: Vec<(Room, u32)>,
+    }
+
+    impl FsmEnum<Room, Log, Walk> for Room {
+        fn create(enum_value: &Room) -> Box<dyn Stateful<Room, Log, Walk> + Send> {
+            Box::new(Visited {
+                id: enum_value.clone(),
+                visits: 0,
+            })
+        }
+    }
+
+    struct Visited {
+        id: Room,
+        visits: u32,
+    }
+
+    #[async_trait]
+    impl Stateful<Room, Log, Walk> for Visited {
+        async fn on_enter(&mut self, context: &mut Log) -> Response<Room> {
+            self.visits += 1;
+            context.entries.push((self.id.clone(), self.visits));
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, event: &Walk, _context: &mut Log) -> Response<Room> {
+            match event {
+                Walk::To(room) => Response::Transition(room.clone()),
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut Log) {}
+    }
+
+    #[tokio::test]
+    async fn test_recreate_on_entry_and_reinit() {
+        let mut sm =
+            StateMachine::new(Log::default(), None).with_cache_policy(CachePolicy::RecreateOnEntry);
+        sm.init(Room::Hall).await.unwrap();
+        sm.process_event(&Walk::To(Room::Kitchen)).await.unwrap();
+        sm.process_event(&Walk::To(Room::Hall)).await.unwrap();
+
+        sm.reset().await;
+        assert!(sm.get_current_state().is_none());
+        sm.reinit(Room::Kitchen).await.unwrap();
+        assert_eq!(
+            sm.get_context().entries,
+            vec![
+                (Room::Hall, 1),
+                (Room::Kitchen, 1),
+                (Room::Hall, 1),
+                (Room::Kitchen, 1)
+            ]
+        );
+    }
+}
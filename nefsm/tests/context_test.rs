@@ -0,0 +1,130 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum ThermostatState {
+        On,
+        Heating,
+        Idle,
+    }
+
+    #[derive(Debug)]
+    enum ThermostatEvent {
+        Reading(i32),
+    }
+
+    struct Settings {
+        target: i32,
+        notified: Vec<ThermostatState>,
+    }
+
+    impl FsmEnum<ThermostatState, Settings, ThermostatEvent> for ThermostatState {
+        fn create(
+            enum_value: &ThermostatState,
+        ) -> Box<dyn Stateful<ThermostatState, Settings, ThermostatEvent> + Send> {
+            Box::new(Thermostat {
+                id: enum_value.clone(),
+            })
+        }
+
+        fn parent(enum_value: &ThermostatState) -> Option<ThermostatState> {
+            match enum_value {
+                ThermostatState::On => None,
+                _ => Some(ThermostatState::On),
+            }
+        }
+    }
+
+    struct Thermostat {
+        id: ThermostatState,
+    }
+
+    impl Stateful<ThermostatState, Settings, ThermostatEvent> for Thermostat {
+        fn on_enter(&mut self, _context: &mut Settings) -> Response<ThermostatState> {
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            event: &ThermostatEvent,
+            context: &mut Settings,
+        ) -> Response<ThermostatState> {
+            match event {
+                ThermostatEvent::Reading(t) if *t < context.target => {
+                    Response::Transition(ThermostatState::Heating)
+                }
+                ThermostatEvent::Reading(_) => Response::Transition(ThermostatState::Idle),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut Settings) {}
+
+        fn on_context_changed(&mut self, context: &mut Settings) {
+            context.notified.push(self.id.clone());
+        }
+    }
+
+    #[test]
+    fn test_external_context_changes() {
+        let mut sm = StateMachine::new(
+            Settings {
+                target: 20,
+                notified: vec![],
+            },
+            None,
+        );
+        sm.init(ThermostatState::Idle).unwrap();
+        sm.process_event(&ThermostatEvent::Reading(19)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), ThermostatState::Heating);
+
+        // Direct access does not notify the states
+        sm.get_context_mut().target = 18;
+        assert!(sm.get_context().notified.is_empty());
+        sm.process_event(&ThermostatEvent::Reading(19)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), ThermostatState::Idle);
+
+        let previous = sm.with_context(|settings| std::mem::replace(&mut settings.target, 22));
+        assert_eq!(previous, 18);
+        assert_eq!(
+            sm.get_context().notified,
+            vec![ThermostatState::Idle, ThermostatState::On]
+        );
+
+        let (settings, state) = sm.into_parts();
+        assert_eq!(settings.target, 22);
+        assert_eq!(state, Some(ThermostatState::Idle));
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use nefsm::Async::*;
+
+    #[derive(Debug)]
+    enum Event {
+        Next,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum State { First, Second }
+        context: Vec<u32>;
+        event: Event;
+        transitions {
+            First + Next => Second,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_context_access() {
+        let mut sm = StateMachine::new(vec![], None);
+        sm.init(State::First).await.unwrap();
+        sm.get_context_mut().push(1);
+        assert_eq!(sm.with_context(|values| values.len()), 1);
+        sm.process_event(&Event::Next).await.unwrap();
+        assert_eq!(sm.into_parts(), (vec![1], Some(State::Second)));
+    }
+}
@@ -0,0 +1,259 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::coverage::{Coverage, CoverageRecorder};
+    use nefsm::sync::*;
+    use nefsm::table::{Transition, TransitionTable};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum DoorState {
+        Closed,
+        Open,
+        Locked,
+    }
+
+    #[derive(Debug)]
+    enum DoorEvent {
+        Open,
+        Close,
+        Lock,
+        Unlock,
+        Knock,
+    }
+
+    impl FsmEnum<DoorState, (), DoorEvent> for DoorState {
+        fn create(_enum_value: &DoorState) -> Box<dyn Stateful<DoorState, (), DoorEvent> + Send> {
+            Box::new(Door {})
+        }
+    }
+
+    struct Door {}
+
+    impl Stateful<DoorState, (), DoorEvent> for Door {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<DoorState> {
+            Response::Handled
+        }
+
+        fn on_event(&mut self, event: &DoorEvent, _context: &mut ()) -> Response<DoorState> {
+            match event {
+                DoorEvent::Knock => Response::Handled,
+                _ => Response::Error("not in the transition table".to_string()),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    fn door_table() -> TransitionTable<DoorState, (), DoorEvent> {
+        TransitionTable::new()
+            .add_transition(Transition::new(
+                DoorState::Closed,
+                &DoorEvent::Open,
+                DoorState::Open,
+            ))
+            .add_transition(Transition::new(
+                DoorState::Open,
+                &DoorEvent::Close,
+                DoorState::Closed,
+            ))
+            .add_transition(Transition::new(
+                DoorState::Closed,
+                &DoorEvent::Lock,
+                DoorState::Locked,
+            ))
+            .add_transition(Transition::new(
+                DoorState::Locked,
+                &DoorEvent::Unlock,
+                DoorState::Closed,
+            ))
+    }
+
+    fn door(recorder: &CoverageRecorder<DoorState>) -> StateMachine<DoorState, (), DoorEvent> {
+        let mut sm = StateMachine::new((), None)
+            .with_transitions(door_table())
+            .with_coverage(recorder.clone());
+        sm.init(DoorState::Closed).unwrap();
+        sm
+    }
+
+    #[test]
+    fn test_coverage_across_machines() {
+        let recorder = CoverageRecorder::new();
+
+        let mut first = door(&recorder);
+        first.process_event(&DoorEvent::Open).unwrap();
+        first.process_event(&DoorEvent::Close).unwrap();
+        first.process_event(&DoorEvent::Knock).unwrap();
+        assert!(first.process_event(&DoorEvent::Close).is_err());
+
+        let mut second = door(&recorder);
+        second.process_event(&DoorEvent::Open).unwrap();
+
+        let coverage = recorder.get_coverage();
+        assert_eq!(
+            coverage.get_states(),
+            &[(DoorState::Closed, 3), (DoorState::Open, 2)]
+        );
+        assert!(coverage.get_transitions().contains(&(
+            (DoorState::Closed, "Knock".to_string(), DoorState::Closed),
+            1
+        )));
+        assert_eq!(
+            coverage.get_errors(),
+            &[((DoorState::Closed, "Close".to_string()), 1)]
+        );
+
+        let report = recorder.report(&door_table());
+        assert_eq!(
+            report
+                .edges
+                .iter()
+                .map(|edge| edge.hits)
+                .collect::<Vec<_>>(),
+            vec![2, 1, 0, 0]
+        );
+        assert_eq!(report.untested_edges().len(), 2);
+        assert_eq!(report.unvisited_states, vec![DoorState::Locked]);
+        assert!(report.undeclared_transitions.is_empty());
+        assert!(!report.is_complete());
+
+        let text = report.to_string();
+        assert!(text.starts_with("2/4 edges covered\n"));
+        assert!(text.contains("  Closed + Lock => Locked: UNTESTED\n"));
+        assert!(text.contains("error: Closed + Close (1 times)\n"));
+
+        let json = report.to_json();
+        assert!(json.starts_with("{\"covered_edges\":2,\"total_edges\":4,"));
+        assert!(
+            json.contains("{\"from\":\"Open\",\"event\":\"Close\",\"to\":\"Closed\",\"hits\":1}")
+        );
+        assert!(json.contains("\"unvisited_states\":[\"Locked\"]"));
+    }
+
+    #[test]
+    fn test_merge_coverage() {
+        let locking = CoverageRecorder::new();
+        let mut sm = door(&locking);
+        sm.process_event(&DoorEvent::Lock).unwrap();
+        sm.process_event(&DoorEvent::Unlock).unwrap();
+
+        let opening = CoverageRecorder::new();
+        let mut sm = door(&opening);
+        sm.process_event(&DoorEvent::Open).unwrap();
+        sm.process_event(&DoorEvent::Close).unwrap();
+
+        let mut coverage = Coverage::new();
+        coverage.merge(&locking.get_coverage());
+        coverage.merge(&opening.get_coverage());
+        assert_eq!(coverage.get_states()[0], (DoorState::Closed, 4));
+        let report = coverage.report(&door_table());
+        assert!(report.is_complete(), "{}", report);
+    }
+}
+
+#[cfg(test)]
+mod redirect_tests {
+    use nefsm::coverage::CoverageRecorder;
+    use nefsm::sync::*;
+    use nefsm::table::{Transition, TransitionTable};
+
+    #[derive(Debug)]
+    enum ValveEvent {
+        Turn,
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Valve { Shut, Opening, Open }
+        context: ();
+        event: ValveEvent;
+        transitions {
+            Shut + Turn => Opening,
+            Open + Turn => Shut,
+        }
+        on_enter {
+            Opening => |_ctx| Response::Transition(Valve::Open),
+        }
+    }
+
+    fn valve_table() -> TransitionTable<Valve, (), ValveEvent> {
+        TransitionTable::new()
+            .add_transition(Transition::new(
+                Valve::Shut,
+                &ValveEvent::Turn,
+                Valve::Opening,
+            ))
+            .add_transition(Transition::new(
+                Valve::Open,
+                &ValveEvent::Turn,
+                Valve::Shut,
+            ))
+    }
+
+    #[test]
+    fn test_edge_followed_by_redirect() {
+        let recorder = CoverageRecorder::new();
+        let mut sm = StateMachine::new((), None)
+            .with_transitions(valve_table())
+            .with_coverage(recorder.clone());
+        sm.init(Valve::Shut).unwrap();
+        sm.process_event(&ValveEvent::Turn).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), Valve::Open);
+        sm.process_event(&ValveEvent::Turn).unwrap();
+
+        // The Shut + Turn edge fired even though Opening moved straight on to Open
+        let report = recorder.report(&valve_table());
+        assert!(report.is_complete(), "{}", report);
+        assert!(report.undeclared_transitions.is_empty());
+        assert_eq!(
+            recorder.get_coverage().get_transitions(),
+            &[
+                ((Valve::Shut, "Turn".to_string(), Valve::Opening), 1),
+                ((Valve::Open, "Turn".to_string(), Valve::Shut), 1)
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use nefsm::coverage::CoverageRecorder;
+    use nefsm::Async::*;
+
+    #[derive(Debug)]
+    enum LampEvent {
+        Toggle,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum Lamp { Off, On }
+        context: ();
+        event: LampEvent;
+        transitions {
+            Off + Toggle => On,
+            On + Toggle => Off,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_coverage() {
+        let recorder = CoverageRecorder::new();
+        let mut sm = StateMachine::new((), None).with_coverage(recorder.clone());
+        sm.init(Lamp::Off).await.unwrap();
+        sm.process_event(&LampEvent::Toggle).await.unwrap();
+        sm.process_event(&LampEvent::Toggle).await.unwrap();
+        sm.process_event(&LampEvent::Toggle).await.unwrap();
+
+        let coverage = recorder.get_coverage();
+        assert_eq!(coverage.get_states(), &[(Lamp::Off, 2), (Lamp::On, 2)]);
+        assert_eq!(
+            coverage.get_transitions(),
+            &[
+                ((Lamp::Off, "Toggle".to_string(), Lamp::On), 2),
+                ((Lamp::On, "Toggle".to_string(), Lamp::Off), 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CallState {
+        Idle,
+        Connecting,
+        Ringing,
+        Connected,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum CallEvent {
+        Dial,
+        Alert,
+        Answer,
+        Digit(u8),
+    }
+
+    impl FsmEnum<CallState, Vec<u8>, CallEvent> for CallState {
+        fn create(
+            enum_value: &CallState,
+        ) -> Box<dyn Stateful<CallState, Vec<u8>, CallEvent> + Send> {
+            Box::new(Call {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Call {
+        id: CallState,
+    }
+
+    impl Stateful<CallState, Vec<u8>, CallEvent> for Call {
+        fn on_enter(&mut self, _context: &mut Vec<u8>) -> Response<CallState> {
+            Response::Handled
+        }
+
+        fn on_event(&mut self, event: &CallEvent, digits: &mut Vec<u8>) -> Response<CallState> {
+            match (&self.id, event) {
+                (CallState::Idle, CallEvent::Dial) => Response::Transition(CallState::Connecting),
+                (CallState::Connecting, CallEvent::Alert) => {
+                    Response::Transition(CallState::Ringing)
+                }
+                (CallState::Ringing, CallEvent::Answer) => {
+                    Response::Transition(CallState::Connected)
+                }
+                (CallState::Connected, CallEvent::Digit(d)) => {
+                    digits.push(*d);
+                    Response::Handled
+                }
+                _ => Response::Error(format!("{:?} is not valid in {:?}", event, self.id)),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut Vec<u8>) {}
+
+        fn is_deferred(&self, event: &CallEvent, _context: &Vec<u8>) -> bool {
+            match self.id {
+                CallState::Connecting => matches!(event, CallEvent::Answer | CallEvent::Digit(_)),
+                CallState::Ringing => matches!(event, CallEvent::Digit(_)),
+                _ => false,
+            }
+        }
+    }
+
+    #[test]
+    fn test_deferred_events_replayed_in_order() {
+        let mut sm = StateMachine::new(vec![], None).with_deferred_events();
+        sm.init(CallState::Idle).unwrap();
+        sm.process_event(&CallEvent::Dial).unwrap();
+
+        sm.process_event(&CallEvent::Digit(1)).unwrap();
+        sm.process_event(&CallEvent::Answer).unwrap();
+        sm.process_event(&CallEvent::Digit(2)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Connecting);
+        assert_eq!(
+            sm.get_deferred_events(),
+            [CallEvent::Digit(1), CallEvent::Answer, CallEvent::Digit(2)]
+        );
+
+        // Ringing still defers the digits but handles Answer, which then releases the digits
+        sm.process_event(&CallEvent::Alert).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Connected);
+        assert!(sm.get_deferred_events().is_empty());
+        assert_eq!(*sm.get_context(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_failing_replayed_event_is_reported() {
+        let mut sm = StateMachine::new(vec![], None).with_deferred_events();
+        sm.init(CallState::Idle).unwrap();
+        sm.process_event(&CallEvent::Dial).unwrap();
+        sm.process_event(&CallEvent::Answer).unwrap();
+        sm.process_event(&CallEvent::Answer).unwrap();
+
+        // The first Answer connects the call, so the second one is invalid once replayed
+        match sm.process_event(&CallEvent::Alert).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidEvent(e)) => assert_eq!(e, "Answer is not valid in Connected"),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("the replayed Answer should be reported"),
+        }
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Connected);
+        assert!(sm.get_deferred_events().is_empty());
+    }
+
+    #[test]
+    fn test_deferral_requires_opt_in() {
+        let mut sm = StateMachine::new(vec![], None);
+        sm.init(CallState::Connecting).unwrap();
+        assert!(matches!(
+            sm.process_event(&CallEvent::Answer).map_err(Error::into_kind),
+            Err(ErrorKind::InternalError(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum State {
+        Busy,
+        Ready,
+        Done,
+    }
+
+    #[derive(Debug, Clone)]
+    enum Event {
+        Work,
+        Free,
+    }
+
+    impl FsmEnum<State, (), Event> for State {
+        fn create(enum_value: &State) -> Box<dyn Stateful<State, (), Event> + Send> {
+            Box::new(Worker {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Worker {
+        id: State,
+    }
+
+    #[async_trait]
+    impl Stateful<State, (), Event> for Worker {
+        async fn on_enter(&mut self, _context: &mut ()) -> Response<State> {
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, event: &Event, _context: &mut ()) -> Response<State> {
+            match (&self.id, event) {
+                (State::Busy, Event::Free) => Response::Transition(State::Ready),
+                (State::Ready, Event::Work) => Response::Transition(State::Done),
+                _ => Response::Unhandled,
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut ()) {}
+
+        fn is_deferred(&self, event: &Event, _context: &()) -> bool {
+            self.id == State::Busy && matches!(event, Event::Work)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_deferred_event() {
+        let mut sm = StateMachine::new((), None).with_deferred_events();
+        sm.init(State::Busy).await.unwrap();
+        sm.process_event(&Event::Work).await.unwrap();
+        assert_eq!(sm.get_deferred_events().len(), 1);
+        sm.process_event(&Event::Free).await.unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::Done);
+        assert!(sm.get_deferred_events().is_empty());
+    }
+}
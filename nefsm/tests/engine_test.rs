@@ -0,0 +1,204 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::error::ErrorKind;
+    use nefsm::observer::TransitionObserver;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone)]
+    pub enum CallEvent {
+        Dial,
+        Answer,
+        Hold,
+        HangUp,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq)]
+    pub struct CallContext {
+        pub attempts: u32,
+        pub holds: u32,
+    }
+
+    // Define the same machine for both modules: hierarchical states, a table, on_enter redirects and an error
+    mod sync_machine {
+        use super::{CallContext, CallEvent};
+        use nefsm::sync::Response;
+
+        nefsm::fsm! {
+            sync;
+            pub enum CallState { Idle, Active, Dialing, Connected, OnHold, Busy }
+            context: CallContext;
+            event: CallEvent;
+            parents {
+                Dialing => Active,
+                Connected => Active,
+                OnHold => Connected,
+            }
+            transitions {
+                Idle + Dial => Dialing,
+                Dialing + Answer => Connected,
+                Connected + Hold => OnHold / |ctx, _event| ctx.holds += 1,
+                OnHold + Hold => Busy,
+                Active + HangUp => Idle,
+            }
+            on_enter {
+                Dialing => |ctx| {
+                    ctx.attempts += 1;
+                    if ctx.attempts > 2 {
+                        Response::Transition(CallState::Idle)
+                    } else {
+                        Response::Handled
+                    }
+                },
+                Busy => |_ctx| Response::Error("line busy".to_string()),
+            }
+        }
+    }
+
+    mod async_machine {
+        use super::{CallContext, CallEvent};
+        use nefsm::Async::Response;
+
+        nefsm::fsm! {
+            Async;
+            pub enum CallState { Idle, Active, Dialing, Connected, OnHold, Busy }
+            context: CallContext;
+            event: CallEvent;
+            parents {
+                Dialing => Active,
+                Connected => Active,
+                OnHold => Connected,
+            }
+            transitions {
+                Idle + Dial => Dialing,
+                Dialing + Answer => Connected,
+                Connected + Hold => OnHold / |ctx, _event| ctx.holds += 1,
+                OnHold + Hold => Busy,
+                Active + HangUp => Idle,
+            }
+            on_enter {
+                Dialing => |ctx| {
+                    ctx.attempts += 1;
+                    if ctx.attempts > 2 {
+                        Response::Transition(CallState::Idle)
+                    } else {
+                        Response::Handled
+                    }
+                },
+                Busy => |_ctx| Response::Error("line busy".to_string()),
+            }
+        }
+    }
+
+    struct Recorder {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl<S: Debug, E: Debug> TransitionObserver<S, E> for Recorder {
+        fn before_exit(&mut self, state: &S) {
+            self.log.lock().unwrap().push(format!("exit {:?}", state));
+        }
+
+        fn after_enter(&mut self, state: &S, _elapsed: Duration) {
+            self.log.lock().unwrap().push(format!("enter {:?}", state));
+        }
+
+        fn after_event(&mut self, state: &S, event: &E, _elapsed: Duration) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{:?} handled {:?}", state, event));
+        }
+
+        fn on_error(&mut self, state: &S, error: &str) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{:?} failed: {}", state, error));
+        }
+
+        fn on_no_transition(&mut self, state: &S, event: &E) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("{:?} stayed after {:?}", state, event));
+        }
+    }
+
+    const EVENTS: [CallEvent; 11] = [
+        CallEvent::Answer,
+        CallEvent::Dial,
+        CallEvent::Answer,
+        CallEvent::Hold,
+        CallEvent::Hold,
+        CallEvent::HangUp,
+        CallEvent::Dial,
+        CallEvent::HangUp,
+        CallEvent::Dial,
+        CallEvent::Hold,
+        CallEvent::HangUp,
+    ];
+
+    // Define a helper describing the outcome of each event as the same text for both modules
+    fn outcome<S: Debug>(
+        result: Result<(), nefsm::error::Error<S, String>>,
+        state: Option<&S>,
+    ) -> String {
+        match result.map_err(nefsm::error::Error::into_kind) {
+            Ok(()) => format!("ok in {:?}", state),
+            Err(ErrorKind::StateInvalid(e)) => format!("state invalid: {} in {:?}", e, state),
+            Err(e) => format!("error {:?} in {:?}", e, state),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_and_async_behave_identically() {
+        let sync_log = Arc::new(Mutex::new(Vec::new()));
+        let mut sync_sm = nefsm::sync::StateMachine::new(CallContext::default(), None)
+            .with_transactions()
+            .with_observer(Box::new(Recorder {
+                log: sync_log.clone(),
+            }));
+        let async_log = Arc::new(Mutex::new(Vec::new()));
+        let mut async_sm = nefsm::Async::StateMachine::new(CallContext::default(), None)
+            .with_transactions()
+            .with_observer(Box::new(Recorder {
+                log: async_log.clone(),
+            }));
+
+        sync_sm.init(sync_machine::CallState::Idle).unwrap();
+        async_sm.init(async_machine::CallState::Idle).await.unwrap();
+
+        let mut sync_outcomes = Vec::new();
+        let mut async_outcomes = Vec::new();
+        for event in &EVENTS {
+            let result = sync_sm.process_event(event);
+            sync_outcomes.push(outcome(result, sync_sm.get_current_state()));
+            let result = async_sm.process_event(event).await;
+            async_outcomes.push(outcome(result, async_sm.get_current_state()));
+        }
+
+        assert_eq!(sync_outcomes, async_outcomes);
+        assert_eq!(*sync_log.lock().unwrap(), *async_log.lock().unwrap());
+        assert_eq!(sync_sm.get_context(), async_sm.get_context());
+        assert_eq!(sync_sm.get_context().attempts, 3);
+        assert_eq!(sync_sm.get_context().holds, 1);
+        assert!(sync_outcomes[4].starts_with("state invalid: line busy in Some(OnHold)"));
+        assert_eq!(sync_outcomes.last().unwrap(), "ok in Some(Idle)");
+    }
+
+    #[test]
+    fn test_response_is_shared() {
+        // The two modules name the same Response type
+        fn transition(state: u8) -> nefsm::sync::Response<u8> {
+            nefsm::Async::Response::Transition(state)
+        }
+        assert!(matches!(
+            transition(1),
+            nefsm::response::Response::Transition(1)
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum State {
+        Idle,
+        Ping,
+        Pong,
+        Booting,
+        Loading,
+        Ready,
+    }
+
+    #[derive(Debug)]
+    enum Event {
+        Bounce,
+        Boot,
+    }
+
+    impl FsmEnum<State, u32, Event> for State {
+        fn create(enum_value: &State) -> Box<dyn Stateful<State, u32, Event> + Send> {
+            Box::new(Step {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Step {
+        id: State,
+    }
+
+    impl Stateful<State, u32, Event> for Step {
+        fn on_enter(&mut self, context: &mut u32) -> Response<State> {
+            *context += 1;
+            match self.id {
+                State::Ping => Response::Transition(State::Pong),
+                State::Pong => Response::Transition(State::Ping),
+                State::Booting => Response::Transition(State::Loading),
+                State::Loading => Response::Transition(State::Ready),
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(&mut self, event: &Event, _context: &mut u32) -> Response<State> {
+            match event {
+                Event::Bounce => Response::Transition(State::Ping),
+                Event::Boot => Response::Transition(State::Booting),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut u32) {}
+    }
+
+    #[test]
+    fn test_endless_chain_is_stopped() {
+        let mut sm = StateMachine::new(0, None).with_max_enter_chain(4);
+        sm.init(State::Idle).unwrap();
+
+        let e = sm.process_event(&Event::Bounce).unwrap_err();
+        assert_eq!(e.get_from(), Some(&State::Idle));
+        assert_eq!(e.get_to(), Some(&State::Ping));
+        match e.into_kind() {
+            ErrorKind::EnterChainTooLong(chain) => assert_eq!(
+                chain,
+                vec![
+                    State::Ping,
+                    State::Pong,
+                    State::Ping,
+                    State::Pong,
+                    State::Ping,
+                    State::Pong
+                ]
+            ),
+            e => panic!("unexpected error {:?}", e),
+        }
+        assert_eq!(*sm.get_current_state().unwrap(), State::Idle);
+        // Idle, then Ping, Pong, Ping, Pong, Ping
+        assert_eq!(*sm.get_context(), 6);
+    }
+
+    #[test]
+    fn test_chain_within_limit() {
+        let mut sm = StateMachine::new(0, None).with_max_enter_chain(2);
+        sm.init(State::Idle).unwrap();
+        sm.process_event(&Event::Boot).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::Ready);
+
+        let mut sm = StateMachine::new(0, None).with_max_enter_chain(1);
+        assert!(matches!(
+            sm.init(State::Booting).map_err(Error::into_kind),
+            Err(ErrorKind::EnterChainTooLong(_))
+        ));
+        assert_eq!(sm.get_current_state(), None);
+    }
+}
+
+#[cfg(test)]
+mod plain_state_tests {
+    use nefsm::sync::*;
+
+    // Define a state enum without Debug, which machines do not require
+    #[derive(Hash, PartialEq, Eq, Clone)]
+    enum Light {
+        Off,
+        Warming,
+        On,
+    }
+
+    #[derive(Debug)]
+    enum Switch {
+        Press,
+    }
+
+    impl FsmEnum<Light, (), Switch> for Light {
+        fn create(enum_value: &Light) -> Box<dyn Stateful<Light, (), Switch> + Send> {
+            Box::new(Lamp {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Lamp {
+        id: Light,
+    }
+
+    impl Stateful<Light, (), Switch> for Lamp {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<Light> {
+            match self.id {
+                Light::Warming => Response::Transition(Light::On),
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(&mut self, _event: &Switch, _context: &mut ()) -> Response<Light> {
+            Response::Transition(Light::Warming)
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[test]
+    fn test_chain_without_debug_states() {
+        let mut sm = StateMachine::new((), None).with_max_enter_chain(1);
+        assert!(sm.init(Light::Off).is_ok());
+        assert!(sm.process_event(&Switch::Press).is_ok());
+        assert!(sm.get_current_state() == Some(&Light::On));
+
+        let mut sm = StateMachine::new((), None).with_max_enter_chain(0);
+        assert!(sm.init(Light::Off).is_ok());
+        assert!(matches!(
+            sm.process_event(&Switch::Press).map_err(Error::into_kind),
+            Err(ErrorKind::EnterChainTooLong(_))
+        ));
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum State {
+        Ping,
+        Pong,
+    }
+
+    #[derive(Debug)]
+    enum Event {}
+
+    impl FsmEnum<State, (), Event> for State {
+        fn create(enum_value: &State) -> Box<dyn Stateful<State, (), Event> + Send> {
+            Box::new(Step {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Step {
+        id: State,
+    }
+
+    #[async_trait]
+    impl Stateful<State, (), Event> for Step {
+        async fn on_enter(&mut self, _context: &mut ()) -> Response<State> {
+            match self.id {
+                State::Ping => Response::Transition(State::Pong),
+                State::Pong => Response::Transition(State::Ping),
+            }
+        }
+
+        async fn on_event(&mut self, _event: &Event, _context: &mut ()) -> Response<State> {
+            Response::Unhandled
+        }
+
+        async fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[tokio::test]
+    async fn test_default_limit_stops_the_loop() {
+        let mut sm = StateMachine::new((), None);
+        match sm.init(State::Ping).await.map_err(Error::into_kind) {
+            Err(ErrorKind::EnterChainTooLong(chain)) => assert_eq!(chain.len(), 66),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
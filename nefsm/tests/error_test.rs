@@ -1,3 +1,5 @@
+#![cfg(feature = "std")]
+
 #[cfg(test)]
 mod tests {
     use nefsm::sync::*;
@@ -83,17 +85,23 @@ mod tests {
         sm.init(TestState::State1).unwrap();
 
         assert_eq!(*sm.get_current_state().unwrap(), TestState::State1);
-        match sm.process_event(&TestEvent::InvalidEvent) {
+        match sm
+            .process_event(&TestEvent::InvalidEvent)
+            .map_err(Error::into_kind)
+        {
             Ok(_) => panic!("event1 should raise an error"),
-            Err(Error::InvalidEvent(e)) => assert_eq!("cannot handle event1".to_string(), e),
+            Err(ErrorKind::InvalidEvent(e)) => assert_eq!("cannot handle event1".to_string(), e),
             Err(e) => panic!("unexpected error {:?}", e),
         }
 
         assert_eq!(*sm.get_current_state().unwrap(), TestState::State1);
 
-        match sm.process_event(&TestEvent::TransitionToState2) {
+        match sm
+            .process_event(&TestEvent::TransitionToState2)
+            .map_err(Error::into_kind)
+        {
             Ok(_) => panic!("first transition should fail"),
-            Err(Error::StateInvalid(e)) => {
+            Err(ErrorKind::StateInvalid(e)) => {
                 assert_eq!("counter needs to be 2 to enter TestState2".to_string(), e)
             }
             Err(e) => panic!("unexpected error {:?}", e),
@@ -108,4 +116,115 @@ mod tests {
 
         assert_eq!(*sm.get_current_state().unwrap(), TestState::State2);
     }
+
+    #[test]
+    fn test_error_context() {
+        let mut sm = StateMachine::new(TestContext { counter: 0 }, None);
+        sm.init(TestState::State1).unwrap();
+
+        let e = sm.process_event(&TestEvent::InvalidEvent).unwrap_err();
+        assert_eq!(e.get_event(), Some("InvalidEvent"));
+        assert_eq!(e.get_from(), Some(&TestState::State1));
+        assert_eq!(e.get_to(), None);
+        assert_eq!(
+            e.to_string(),
+            "invalid event: cannot handle event1 (event InvalidEvent, in state State1)"
+        );
+
+        // The failing on_enter is reported as the target, the state left unchanged as the source
+        let e = sm
+            .process_event(&TestEvent::TransitionToState2)
+            .unwrap_err();
+        assert_eq!(e.get_from(), Some(&TestState::State1));
+        assert_eq!(e.get_to(), Some(&TestState::State2));
+        assert_eq!(e.get_event(), Some("TransitionToState2"));
+
+        let boxed: Box<dyn std::error::Error> = Box::new(e);
+        assert!(boxed.to_string().starts_with("state cannot be entered"));
+    }
+}
+
+#[cfg(test)]
+mod typed_error_tests {
+    use nefsm::sync::*;
+    use std::fmt;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum PumpState {
+        Off,
+        On,
+    }
+
+    #[derive(Debug)]
+    enum PumpEvent {
+        Start(u32),
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum PumpError {
+        Overpressure(u32),
+    }
+
+    impl fmt::Display for PumpError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                PumpError::Overpressure(bar) => write!(f, "overpressure at {} bar", bar),
+            }
+        }
+    }
+
+    impl FsmEnum<PumpState, (), PumpEvent, PumpError> for PumpState {
+        fn create(
+            enum_value: &PumpState,
+        ) -> Box<dyn Stateful<PumpState, (), PumpEvent, PumpError> + Send> {
+            Box::new(Pump {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Pump {
+        id: PumpState,
+    }
+
+    impl Stateful<PumpState, (), PumpEvent, PumpError> for Pump {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<PumpState, PumpError> {
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            event: &PumpEvent,
+            _context: &mut (),
+        ) -> Response<PumpState, PumpError> {
+            match (&self.id, event) {
+                (PumpState::Off, PumpEvent::Start(bar)) if *bar > 10 => {
+                    Response::Error(PumpError::Overpressure(*bar))
+                }
+                (PumpState::Off, PumpEvent::Start(_)) => Response::Transition(PumpState::On),
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[test]
+    fn test_typed_error() {
+        let mut sm = StateMachine::new((), None);
+        sm.init(PumpState::Off).unwrap();
+
+        let e = sm.process_event(&PumpEvent::Start(12)).unwrap_err();
+        assert_eq!(
+            e.kind(),
+            &ErrorKind::InvalidEvent(PumpError::Overpressure(12))
+        );
+        assert_eq!(
+            e.to_string(),
+            "invalid event: overpressure at 12 bar (event Start(12), in state Off)"
+        );
+
+        sm.process_event(&PumpEvent::Start(8)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), PumpState::On);
+    }
 }
@@ -0,0 +1,132 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CallState {
+        Idle,
+        Connected,
+        Disconnected,
+    }
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Answer,
+        HangUp,
+    }
+
+    impl FsmEnum<CallState, u32, CallEvent> for CallState {
+        fn create(enum_value: &CallState) -> Box<dyn Stateful<CallState, u32, CallEvent> + Send> {
+            Box::new(Call {
+                id: enum_value.clone(),
+            })
+        }
+
+        fn is_final(enum_value: &CallState) -> bool {
+            *enum_value == CallState::Disconnected
+        }
+    }
+
+    struct Call {
+        id: CallState,
+    }
+
+    impl Stateful<CallState, u32, CallEvent> for Call {
+        fn on_enter(&mut self, _context: &mut u32) -> Response<CallState> {
+            Response::Handled
+        }
+
+        fn on_event(&mut self, event: &CallEvent, context: &mut u32) -> Response<CallState> {
+            *context += 1;
+            match (&self.id, event) {
+                (CallState::Idle, CallEvent::Answer) => Response::Transition(CallState::Connected),
+                (_, CallEvent::HangUp) => Response::Transition(CallState::Disconnected),
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut u32) {}
+    }
+
+    #[test]
+    fn test_finished_machine_rejects_events() {
+        let mut sm = StateMachine::new(0, None);
+        assert!(!sm.is_finished());
+        sm.init(CallState::Idle).unwrap();
+        sm.process_event(&CallEvent::Answer).unwrap();
+        assert!(!sm.is_finished());
+        sm.process_event(&CallEvent::HangUp).unwrap();
+        assert!(sm.is_finished());
+
+        let e = sm.process_event(&CallEvent::Answer).unwrap_err();
+        assert_eq!(e.kind(), &ErrorKind::Finished);
+        assert_eq!(e.get_from(), Some(&CallState::Disconnected));
+        // The final state never saw the event
+        assert_eq!(*sm.get_context(), 2);
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use nefsm::Async::*;
+
+    #[derive(Debug)]
+    enum Event {
+        Charge(u32),
+        Unplug,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum Battery { Charging, Full }
+        context: u32;
+        event: Event;
+        final { Full }
+        transitions {
+            Charging + Charge => Charging / |level, event| {
+                if let Event::Charge(amount) = event {
+                    *level += amount;
+                }
+            },
+            Charging + Unplug => Full,
+        }
+    }
+
+    fn battery() -> StateMachine<Battery, u32, Event> {
+        StateMachine::new(0, None)
+    }
+
+    #[tokio::test]
+    async fn test_completion_future() {
+        let mut sm = battery();
+        sm.init(Battery::Charging).await.unwrap();
+        let completion = sm.completion();
+        let waiter = tokio::spawn(completion);
+
+        sm.process_event(&Event::Charge(40)).await.unwrap();
+        sm.process_event(&Event::Charge(60)).await.unwrap();
+        assert!(!sm.is_finished());
+        sm.process_event(&Event::Unplug).await.unwrap();
+
+        assert_eq!(waiter.await.unwrap(), Some((Battery::Full, 100)));
+        assert!(matches!(
+            sm.process_event(&Event::Unplug)
+                .await
+                .map_err(Error::into_kind),
+            Err(ErrorKind::Finished)
+        ));
+        // A machine that already finished resolves right away
+        assert_eq!(sm.completion().await, Some((Battery::Full, 100)));
+    }
+
+    #[tokio::test]
+    async fn test_completion_without_finishing() {
+        let mut sm = battery();
+        sm.init(Battery::Charging).await.unwrap();
+        let completion = sm.completion();
+        drop(sm);
+        assert_eq!(completion.await, None);
+    }
+}
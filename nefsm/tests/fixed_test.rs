@@ -0,0 +1,214 @@
+#[cfg(test)]
+mod tests {
+    use nefsm::error::{Error, ErrorKind};
+    use nefsm::fixed::{FsmEnum, StateMachine, Stateful};
+    use nefsm::response::Response;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Session {
+        Idle,
+        Connecting,
+        Open,
+        Closed,
+        // Not given a slot by the machines below
+        Spare,
+    }
+
+    #[derive(Debug)]
+    enum SessionEvent {
+        Connect,
+        Ack,
+        Timeout,
+        Close,
+    }
+
+    #[derive(Default)]
+    struct Counters {
+        entries: u32,
+        exits: u32,
+    }
+
+    // Define the objects of the states; Connecting counts its attempts across entries
+    enum Handler {
+        Idle,
+        Connecting { attempts: u32 },
+        Open,
+        Closed,
+    }
+
+    impl FsmEnum<Counters, SessionEvent> for Session {
+        type State = Handler;
+
+        fn create(&self) -> Handler {
+            match self {
+                Session::Idle | Session::Spare => Handler::Idle,
+                Session::Connecting => Handler::Connecting { attempts: 0 },
+                Session::Open => Handler::Open,
+                Session::Closed => Handler::Closed,
+            }
+        }
+
+        fn index(&self) -> usize {
+            *self as usize
+        }
+
+        fn is_final(&self) -> bool {
+            *self == Session::Closed
+        }
+    }
+
+    impl Stateful<Session, Counters, SessionEvent> for Handler {
+        fn on_enter(&mut self, counters: &mut Counters) -> Response<Session> {
+            counters.entries += 1;
+            match self {
+                Handler::Connecting { attempts } if *attempts == 3 => {
+                    Response::Error("too many attempts")
+                }
+                Handler::Connecting { attempts } => {
+                    *attempts += 1;
+                    Response::Handled
+                }
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(
+            &mut self,
+            event: &SessionEvent,
+            _counters: &mut Counters,
+        ) -> Response<Session> {
+            match (self, event) {
+                (Handler::Idle, SessionEvent::Connect) => Response::Transition(Session::Connecting),
+                (Handler::Connecting { .. }, SessionEvent::Ack) => {
+                    Response::Transition(Session::Open)
+                }
+                (Handler::Connecting { .. }, SessionEvent::Timeout) => {
+                    Response::Transition(Session::Idle)
+                }
+                (_, SessionEvent::Close) => Response::Transition(Session::Closed),
+                (_, SessionEvent::Timeout) => Response::Handled,
+                _ => Response::Error("unexpected event"),
+            }
+        }
+
+        fn on_exit(&mut self, counters: &mut Counters) {
+            counters.exits += 1;
+        }
+    }
+
+    type SessionMachine = StateMachine<Session, Counters, SessionEvent, &'static str, 4>;
+
+    #[test]
+    fn test_state_objects_are_kept() {
+        let mut sm = SessionMachine::new(Counters::default());
+        sm.init(Session::Idle).unwrap();
+        for _ in 0..3 {
+            sm.process_event(&SessionEvent::Connect).unwrap();
+            sm.process_event(&SessionEvent::Timeout).unwrap();
+        }
+        assert_eq!(sm.get_current_state(), Some(&Session::Idle));
+
+        // Connecting was entered three times already, with the same object
+        let e = sm.process_event(&SessionEvent::Connect).unwrap_err();
+        assert_eq!(e.get_from(), Some(&Session::Idle));
+        assert_eq!(e.get_to(), Some(&Session::Connecting));
+        assert_eq!(e.into_kind(), ErrorKind::StateInvalid("too many attempts"));
+        assert_eq!(sm.get_current_state(), Some(&Session::Idle));
+        assert_eq!(sm.get_context().entries, 8);
+    }
+
+    #[test]
+    fn test_final_state_and_errors() {
+        let mut sm = SessionMachine::new(Counters::default());
+        assert!(matches!(
+            sm.process_event(&SessionEvent::Ack)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::StateMachineNotInitialized)
+        ));
+        sm.init(Session::Idle).unwrap();
+        assert!(matches!(
+            sm.process_event(&SessionEvent::Ack)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::InvalidEvent("unexpected event"))
+        ));
+        sm.process_event(&SessionEvent::Connect).unwrap();
+        sm.process_event(&SessionEvent::Ack).unwrap();
+        sm.process_event(&SessionEvent::Close).unwrap();
+        assert!(sm.is_finished());
+        assert!(matches!(
+            sm.process_event(&SessionEvent::Connect)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::Finished)
+        ));
+
+        let (counters, state) = sm.into_parts();
+        assert_eq!(state, Some(Session::Closed));
+        assert_eq!(counters.exits, 3);
+    }
+
+    #[test]
+    fn test_state_beyond_capacity() {
+        let mut sm = SessionMachine::new(Counters::default());
+        match sm.init(Session::Spare).map_err(Error::into_kind) {
+            Err(ErrorKind::StateNotFound(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(sm.get_current_state().is_none());
+
+        let mut sm: StateMachine<Session, Counters, SessionEvent, &'static str, 5> =
+            StateMachine::new(Counters::default());
+        sm.init(Session::Spare).unwrap();
+        sm.reinit(Session::Open).unwrap();
+        assert_eq!(sm.get_context().exits, 1);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Loop {
+        Ping,
+        Pong,
+    }
+
+    struct Bounce(Loop);
+
+    impl FsmEnum<(), ()> for Loop {
+        type State = Bounce;
+
+        fn create(&self) -> Bounce {
+            Bounce(self.clone())
+        }
+
+        fn index(&self) -> usize {
+            self.clone() as usize
+        }
+    }
+
+    impl Stateful<Loop, (), ()> for Bounce {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<Loop> {
+            match self.0 {
+                Loop::Ping => Response::Transition(Loop::Pong),
+                Loop::Pong => Response::Transition(Loop::Ping),
+            }
+        }
+
+        fn on_event(&mut self, _event: &(), _context: &mut ()) -> Response<Loop> {
+            Response::Handled
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[test]
+    fn test_enter_chain_limit() {
+        let mut sm: StateMachine<Loop, (), (), &'static str, 2> =
+            StateMachine::new(()).with_max_enter_chain(3);
+        match sm.init(Loop::Ping).map_err(Error::into_kind) {
+            // Ping, Pong, Ping, Pong, then the Ping it would have entered next
+            Err(ErrorKind::EnterChainLimitExceeded { last, length }) => {
+                assert_eq!(last, Loop::Ping);
+                assert_eq!(length, 5);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(sm.get_current_state().is_none());
+    }
+}
@@ -0,0 +1,158 @@
+#![cfg(feature = "testing")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::fuzz::{Fuzzer, Rng};
+    use nefsm::sync::*;
+    use std::cell::Cell;
+
+    #[derive(Debug)]
+    enum TurnstileEvent {
+        Coin,
+        Push,
+        Kick,
+    }
+
+    #[derive(Default)]
+    struct Counters {
+        coins: u32,
+        passes: u32,
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Turnstile { Locked, Unlocked }
+        context: Counters;
+        event: TurnstileEvent;
+        transitions {
+            Locked + Coin => Unlocked / |ctx, _event| ctx.coins += 1,
+            Unlocked + Coin => Unlocked / |ctx, _event| ctx.coins += 1,
+            Unlocked + Push => Locked / |ctx, _event| ctx.passes += 1,
+            // A kick opens the turnstile without paying
+            Locked + Kick => Unlocked,
+        }
+    }
+
+    fn fuzzer(
+        events: &'static [fn() -> TurnstileEvent],
+    ) -> Fuzzer<Turnstile, Counters, TurnstileEvent> {
+        Fuzzer::new(
+            || StateMachine::new(Counters::default(), None),
+            Turnstile::Locked,
+            move |rng: &mut Rng| rng.choose(events)(),
+        )
+        .with_invariant("every pass was paid for", |_, ctx: &Counters| {
+            ctx.passes <= ctx.coins
+        })
+    }
+
+    #[test]
+    fn test_invariants_hold() {
+        let report = fuzzer(&[|| TurnstileEvent::Coin, || TurnstileEvent::Push])
+            .with_runs(20)
+            .with_max_events(30)
+            .run();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.runs, 20);
+        assert_eq!(report.events, 600);
+        assert_eq!(report.visited_states.len(), 2);
+        assert!(report.transitions.contains(&(
+            Turnstile::Locked,
+            "Coin".to_string(),
+            Turnstile::Unlocked
+        )));
+        assert!(report.transitions.contains(&(
+            Turnstile::Unlocked,
+            "Push".to_string(),
+            Turnstile::Locked
+        )));
+    }
+
+    #[test]
+    fn test_failing_sequence_is_shrunk() {
+        let report = fuzzer(&[
+            || TurnstileEvent::Coin,
+            || TurnstileEvent::Push,
+            || TurnstileEvent::Kick,
+        ])
+        .with_max_events(100)
+        .run();
+        let failure = report
+            .failure
+            .as_ref()
+            .expect("the kick lets people through");
+        assert_eq!(failure.invariant, "every pass was paid for");
+        assert_eq!(failure.state, Some(Turnstile::Locked));
+        assert!(matches!(
+            failure.events[..],
+            [TurnstileEvent::Kick, TurnstileEvent::Push]
+        ));
+        assert!(failure.reproducible);
+        assert!(report
+            .to_string()
+            .contains("broken in state Some(Locked) after 2 events"));
+    }
+
+    #[test]
+    fn test_unreproducible_failure_is_reported_unshrunk() {
+        // The invariant only fails the third time it is checked, so replaying the sequence passes
+        let checks = Cell::new(0);
+        let report = fuzzer(&[|| TurnstileEvent::Coin])
+            .with_invariant("flaky", move |_, _| {
+                checks.set(checks.get() + 1);
+                checks.get() != 3
+            })
+            .with_max_events(10)
+            .run();
+        let failure = report.failure.as_ref().expect("the third check fails");
+        assert_eq!(failure.invariant, "flaky");
+        assert_eq!(failure.state, Some(Turnstile::Unlocked));
+        assert_eq!(failure.events.len(), 2);
+        assert!(!failure.reproducible);
+        assert!(report
+            .to_string()
+            .contains("after 2 events (not reproducible on replay, not shrunk)"));
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Jammed { Stuck }
+        context: Counters;
+        event: TurnstileEvent;
+        transitions {
+            Stuck + Coin => Stuck,
+        }
+        on_enter {
+            Stuck => |_ctx| Response::Error("the turnstile is jammed".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_failed_init_is_reported() {
+        let report = Fuzzer::new(
+            || StateMachine::new(Counters::default(), None),
+            Jammed::Stuck,
+            |_: &mut Rng| TurnstileEvent::Coin,
+        )
+        .with_invariant("never holds", |_, _| false)
+        .run();
+        assert!(!report.is_ok());
+        assert_eq!(report.runs, 1);
+        assert!(report.visited_states.is_empty());
+        let failure = report.failure.as_ref().expect("init fails");
+        assert_eq!(failure.invariant, "the machine is initialized");
+        assert_eq!(failure.state, None);
+        assert!(failure.events.is_empty());
+        assert!(failure.reproducible);
+    }
+
+    #[test]
+    fn test_same_seed_same_sequences() {
+        let draws = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..10).map(|_| rng.below(100)).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(7), draws(7));
+        assert_ne!(draws(7), draws(8));
+    }
+}
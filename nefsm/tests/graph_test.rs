@@ -0,0 +1,160 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::graph;
+    use nefsm::sync::*;
+    use nefsm::table::{Transition, TransitionTable};
+    use std::fmt;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CallState {
+        Idle,
+        Dialing,
+        Connected,
+    }
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Dial(String),
+        Answer,
+        HangUp,
+    }
+
+    impl FsmEnum<CallState, u32, CallEvent> for CallState {
+        fn create(_enum_value: &CallState) -> Box<dyn Stateful<CallState, u32, CallEvent> + Send> {
+            Box::new(Call {})
+        }
+    }
+
+    struct Call {}
+
+    impl Stateful<CallState, u32, CallEvent> for Call {
+        fn on_enter(&mut self, _context: &mut u32) -> Response<CallState> {
+            Response::Handled
+        }
+
+        fn on_event(&mut self, _event: &CallEvent, _context: &mut u32) -> Response<CallState> {
+            Response::Unhandled
+        }
+
+        fn on_exit(&mut self, _context: &mut u32) {}
+    }
+
+    fn call_table() -> TransitionTable<CallState, u32, CallEvent> {
+        TransitionTable::new()
+            .add_transition(
+                Transition::new(
+                    CallState::Idle,
+                    &CallEvent::Dial(String::new()),
+                    CallState::Dialing,
+                )
+                .guard(|retries: &u32, event| {
+                    *retries < 3 && matches!(event, CallEvent::Dial(number) if !number.is_empty())
+                }),
+            )
+            .add_transition(Transition::new(
+                CallState::Dialing,
+                &CallEvent::Answer,
+                CallState::Connected,
+            ))
+            .add_transition(Transition::new(
+                CallState::Connected,
+                &CallEvent::HangUp,
+                CallState::Idle,
+            ))
+    }
+
+    #[test]
+    fn test_dot_export() {
+        assert_eq!(
+            graph::to_dot(&call_table(), None),
+            "digraph fsm {
+    \"Idle\";
+    \"Dialing\";
+    \"Connected\";
+    \"Idle\" -> \"Dialing\" [label=\"Dial [guard]\"];
+    \"Dialing\" -> \"Connected\" [label=\"Answer\"];
+    \"Connected\" -> \"Idle\" [label=\"HangUp\"];
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_live_machine_export_highlights_current_state() {
+        let mut sm = StateMachine::new(0, None).with_transitions(call_table());
+        sm.init(CallState::Idle).unwrap();
+        sm.process_event(&CallEvent::Dial("555".to_string()))
+            .unwrap();
+
+        assert!(sm
+            .to_dot()
+            .contains("    \"Dialing\" [style=filled, fillcolor=lightblue];\n"));
+        assert_eq!(
+            sm.to_mermaid(),
+            "stateDiagram-v2
+    Idle --> Dialing : Dial [guard]
+    Dialing --> Connected : Answer
+    Connected --> Idle : HangUp
+    classDef current fill:#add8e6
+    class Dialing current
+"
+        );
+    }
+
+    #[test]
+    fn test_current_state_without_edges_is_drawn() {
+        let table: TransitionTable<CallState, u32, CallEvent> = TransitionTable::new()
+            .add_transition(Transition::new(
+                CallState::Idle,
+                &CallEvent::Answer,
+                CallState::Dialing,
+            ));
+        assert!(graph::to_dot(&table, Some(&CallState::Connected))
+            .contains("    \"Connected\" [style=filled, fillcolor=lightblue];\n"));
+        assert_eq!(
+            graph::to_mermaid(&table, Some(&CallState::Connected)),
+            "stateDiagram-v2
+    Connected
+    Idle --> Dialing : Answer
+    classDef current fill:#add8e6
+    class Connected current
+"
+        );
+    }
+
+    // A state printed as an arbitrary name
+    #[derive(PartialEq)]
+    struct Named(&'static str);
+
+    impl fmt::Debug for Named {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    #[test]
+    fn test_mermaid_ids_are_unique() {
+        let table: TransitionTable<Named, u32, CallEvent> = TransitionTable::new()
+            .add_transition(Transition::new(
+                Named("A::B"),
+                &CallEvent::Answer,
+                Named("A_B"),
+            ))
+            .add_transition(Transition::new(
+                Named("A_B"),
+                &CallEvent::HangUp,
+                Named("AB"),
+            ));
+        assert_eq!(
+            graph::to_mermaid(&table, None),
+            "stateDiagram-v2
+    A_3a__3a_B : A::B
+    A_5f_B : A_B
+    A_3a__3a_B --> A_5f_B : Answer
+    A_5f_B --> AB : HangUp
+"
+        );
+    }
+}
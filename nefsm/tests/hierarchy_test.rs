@@ -0,0 +1,236 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum TestState {
+        Off,
+        On,
+        Idle,
+        Busy,
+        Working,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        PowerOn,
+        PowerOff,
+        Start,
+        Finish,
+        Ping,
+    }
+
+    struct TestContext {
+        log: Vec<String>,
+    }
+
+    impl FsmEnum<TestState, TestContext, TestEvent> for TestState {
+        fn create(
+            enum_value: &TestState,
+        ) -> Box<dyn Stateful<TestState, TestContext, TestEvent> + Send> {
+            Box::new(TestNode {
+                id: enum_value.clone(),
+            })
+        }
+
+        fn parent(enum_value: &TestState) -> Option<TestState> {
+            match enum_value {
+                TestState::Idle | TestState::Busy => Some(TestState::On),
+                TestState::Working => Some(TestState::Busy),
+                _ => None,
+            }
+        }
+    }
+
+    struct TestNode {
+        id: TestState,
+    }
+
+    impl Stateful<TestState, TestContext, TestEvent> for TestNode {
+        fn on_enter(&mut self, context: &mut TestContext) -> Response<TestState> {
+            context.log.push(format!("enter {:?}", self.id));
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            event: &TestEvent,
+            _context: &mut TestContext,
+        ) -> Response<TestState> {
+            match (&self.id, event) {
+                (TestState::Off, TestEvent::PowerOn) => Response::Transition(TestState::Idle),
+                (TestState::On, TestEvent::PowerOff) => Response::Transition(TestState::Off),
+                (TestState::Idle, TestEvent::Start) => Response::Transition(TestState::Working),
+                (TestState::Busy, TestEvent::Finish) => Response::Transition(TestState::Idle),
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, context: &mut TestContext) {
+            context.log.push(format!("exit {:?}", self.id));
+        }
+    }
+
+    #[test]
+    fn test_enter_exit_follow_common_ancestor() {
+        let mut sm = StateMachine::new(TestContext { log: vec![] }, None);
+        sm.init(TestState::Idle).unwrap();
+        assert_eq!(sm.get_context().log, vec!["enter On", "enter Idle"]);
+        assert!(sm.is_in_state(&TestState::On));
+        assert!(!sm.is_in_state(&TestState::Busy));
+
+        sm.process_event(&TestEvent::Start).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Working);
+        assert!(sm.is_in_state(&TestState::Busy));
+        assert_eq!(
+            sm.get_context().log[2..],
+            ["exit Idle", "enter Busy", "enter Working"]
+        );
+
+        // Leaving the hierarchy exits every nested state, innermost first
+        sm.process_event(&TestEvent::PowerOff).unwrap();
+        sm.process_event(&TestEvent::PowerOn).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Idle);
+        assert_eq!(
+            sm.get_context().log[5..],
+            [
+                "exit Working",
+                "exit Busy",
+                "exit On",
+                "enter Off",
+                "exit Off",
+                "enter On",
+                "enter Idle"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unhandled_events_bubble_to_parent() {
+        let mut sm = StateMachine::new(TestContext { log: vec![] }, None);
+        sm.init(TestState::Working).unwrap();
+
+        // Finish is handled by Busy on behalf of Working
+        sm.process_event(&TestEvent::Finish).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Idle);
+        assert_eq!(
+            sm.get_context().log[3..],
+            ["exit Working", "exit Busy", "enter Idle"]
+        );
+
+        // PowerOff is handled by the root On state
+        sm.process_event(&TestEvent::PowerOff).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Off);
+        assert_eq!(
+            sm.get_context().log[6..],
+            ["exit Idle", "exit On", "enter Off"]
+        );
+
+        // Events nobody handles are ignored
+        sm.process_event(&TestEvent::Ping).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Off);
+        assert_eq!(sm.get_context().log.len(), 9);
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum TestState {
+        Off,
+        On,
+        Idle,
+        Busy,
+        Working,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        PowerOff,
+        Start,
+        Finish,
+    }
+
+    struct TestContext {
+        log: Vec<String>,
+    }
+
+    impl FsmEnum<TestState, TestContext, TestEvent> for TestState {
+        fn create(
+            enum_value: &TestState,
+        ) -> Box<dyn Stateful<TestState, TestContext, TestEvent> + Send> {
+            Box::new(TestNode {
+                id: enum_value.clone(),
+            })
+        }
+
+        fn parent(enum_value: &TestState) -> Option<TestState> {
+            match enum_value {
+                TestState::Idle | TestState::Busy => Some(TestState::On),
+                TestState::Working => Some(TestState::Busy),
+                _ => None,
+            }
+        }
+    }
+
+    struct TestNode {
+        id: TestState,
+    }
+
+    #[async_trait]
+    impl Stateful<TestState, TestContext, TestEvent> for TestNode {
+        async fn on_enter(&mut self, context: &mut TestContext) -> Response<TestState> {
+            context.log.push(format!("enter {:?}", self.id));
+            Response::Handled
+        }
+
+        async fn on_event(
+            &mut self,
+            event: &TestEvent,
+            _context: &mut TestContext,
+        ) -> Response<TestState> {
+            match (&self.id, event) {
+                (TestState::On, TestEvent::PowerOff) => Response::Transition(TestState::Off),
+                (TestState::Idle, TestEvent::Start) => Response::Transition(TestState::Working),
+                (TestState::Busy, TestEvent::Finish) => Response::Transition(TestState::Idle),
+                _ => Response::Unhandled,
+            }
+        }
+
+        async fn on_exit(&mut self, context: &mut TestContext) {
+            context.log.push(format!("exit {:?}", self.id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_hierarchy() {
+        let mut sm = StateMachine::new(TestContext { log: vec![] }, None);
+        sm.init(TestState::Idle).await.unwrap();
+        sm.process_event(&TestEvent::Start).await.unwrap();
+        sm.process_event(&TestEvent::Finish).await.unwrap();
+        sm.process_event(&TestEvent::PowerOff).await.unwrap();
+
+        assert_eq!(*sm.get_current_state().unwrap(), TestState::Off);
+        assert_eq!(
+            sm.get_context().log,
+            vec![
+                "enter On",
+                "enter Idle",
+                "exit Idle",
+                "enter Busy",
+                "enter Working",
+                "exit Working",
+                "exit Busy",
+                "enter Idle",
+                "exit Idle",
+                "exit On",
+                "enter Off",
+            ]
+        );
+    }
+}
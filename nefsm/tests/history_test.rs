@@ -0,0 +1,171 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::history::Outcome;
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum TestState {
+        Green,
+        Yellow,
+        Red,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        Next,
+        Tick,
+        Fault,
+    }
+
+    impl FsmEnum<TestState, (), TestEvent> for TestState {
+        fn create(enum_value: &TestState) -> Box<dyn Stateful<TestState, (), TestEvent> + Send> {
+            Box::new(Light {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Light {
+        id: TestState,
+    }
+
+    impl Stateful<TestState, (), TestEvent> for Light {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<TestState> {
+            Response::Handled
+        }
+
+        fn on_event(&mut self, event: &TestEvent, _context: &mut ()) -> Response<TestState> {
+            match (event, &self.id) {
+                (TestEvent::Next, TestState::Green) => Response::Transition(TestState::Yellow),
+                (TestEvent::Next, TestState::Yellow) => Response::Transition(TestState::Red),
+                (TestEvent::Next, TestState::Red) => Response::Transition(TestState::Green),
+                (TestEvent::Tick, _) => Response::Handled,
+                (TestEvent::Fault, _) => Response::Error("fault".to_string()),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[test]
+    fn test_history_records_path() {
+        let mut sm = StateMachine::new((), None).with_history(3);
+        sm.init(TestState::Green).unwrap();
+
+        sm.process_event(&TestEvent::Next).unwrap();
+        sm.process_event(&TestEvent::Tick).unwrap();
+        assert!(sm.process_event(&TestEvent::Fault).is_err());
+        sm.process_event(&TestEvent::Next).unwrap();
+
+        // Only the last three events are kept
+        let history = sm.history().unwrap();
+        assert_eq!(history.len(), 3);
+        let entries: Vec<_> = history
+            .iter()
+            .map(|e| {
+                (
+                    e.event.as_str(),
+                    e.from.clone(),
+                    e.to.clone(),
+                    e.outcome.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    "Tick",
+                    TestState::Yellow,
+                    TestState::Yellow,
+                    Outcome::Handled
+                ),
+                (
+                    "Fault",
+                    TestState::Yellow,
+                    TestState::Yellow,
+                    Outcome::Error("InvalidEvent(\"fault\")".to_string())
+                ),
+                (
+                    "Next",
+                    TestState::Yellow,
+                    TestState::Red,
+                    Outcome::Transition
+                ),
+            ]
+        );
+
+        let dump = history.dump();
+        assert_eq!(dump.lines().count(), 3);
+        assert!(dump.ends_with("Yellow --Next--> Red (transition)\n"));
+    }
+
+    #[test]
+    fn test_history_disabled_by_default() {
+        let mut sm = StateMachine::new((), None);
+        sm.init(TestState::Green).unwrap();
+        sm.process_event(&TestEvent::Next).unwrap();
+        assert!(sm.history().is_none());
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::history::Outcome;
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum TestState {
+        Off,
+        On,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        Toggle,
+    }
+
+    impl FsmEnum<TestState, (), TestEvent> for TestState {
+        fn create(enum_value: &TestState) -> Box<dyn Stateful<TestState, (), TestEvent> + Send> {
+            Box::new(Switch {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Switch {
+        id: TestState,
+    }
+
+    #[async_trait]
+    impl Stateful<TestState, (), TestEvent> for Switch {
+        async fn on_enter(&mut self, _context: &mut ()) -> Response<TestState> {
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, _event: &TestEvent, _context: &mut ()) -> Response<TestState> {
+            match self.id {
+                TestState::Off => Response::Transition(TestState::On),
+                TestState::On => Response::Transition(TestState::Off),
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[tokio::test]
+    async fn test_async_history() {
+        let mut sm = StateMachine::new((), None).with_history(10);
+        sm.init(TestState::Off).await.unwrap();
+        sm.process_event(&TestEvent::Toggle).await.unwrap();
+        sm.process_event(&TestEvent::Toggle).await.unwrap();
+
+        let history = sm.history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|e| e.outcome == Outcome::Transition));
+        assert_eq!(history.iter().last().unwrap().to, TestState::Off);
+    }
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Dial,
+        Answer,
+        Reject,
+        HangUp,
+        Digit(u8),
+    }
+
+    struct CallContext {
+        retries: u32,
+        digits: Vec<u8>,
+        log: Vec<&'static str>,
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum CallState { Idle, Active, Dialing, Connected }
+        context: CallContext;
+        event: CallEvent;
+        parents {
+            Dialing => Active,
+            Connected => Active,
+        }
+        transitions {
+            Idle + Dial => Dialing,
+            Dialing + Digit => Dialing / |ctx, event| {
+                if let CallEvent::Digit(d) = event {
+                    ctx.digits.push(*d);
+                }
+            },
+            Dialing + Answer => Connected,
+            Active + HangUp => Idle,
+            Dialing + Reject => Idle,
+        }
+        on_enter {
+            Dialing => |ctx| {
+                ctx.retries += 1;
+                if ctx.retries > 2 {
+                    Response::Transition(CallState::Idle)
+                } else {
+                    Response::Handled
+                }
+            },
+        }
+        on_exit {
+            Connected => |ctx| ctx.log.push("hung up"),
+        }
+    }
+
+    #[test]
+    fn test_generated_state_machine() {
+        let mut sm = StateMachine::new(
+            CallContext {
+                retries: 0,
+                digits: vec![],
+                log: vec![],
+            },
+            None,
+        );
+        sm.init(CallState::Idle).unwrap();
+
+        sm.process_event(&CallEvent::Dial).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Dialing);
+        sm.process_event(&CallEvent::Digit(4)).unwrap();
+        sm.process_event(&CallEvent::Digit(2)).unwrap();
+        assert_eq!(sm.get_context().digits, vec![4, 2]);
+
+        sm.process_event(&CallEvent::Answer).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Connected);
+
+        // HangUp is declared on the parent state
+        sm.process_event(&CallEvent::HangUp).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+        assert_eq!(sm.get_context().log, vec!["hung up"]);
+
+        // Events without a transition are ignored
+        sm.process_event(&CallEvent::Answer).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+
+        // The on_enter hook redirects back to Idle after too many retries
+        sm.process_event(&CallEvent::Dial).unwrap();
+        sm.process_event(&CallEvent::Reject).unwrap();
+        sm.process_event(&CallEvent::Dial).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+        assert_eq!(sm.get_context().retries, 3);
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use nefsm::Async::*;
+
+    #[derive(Debug)]
+    enum Event {
+        Start,
+        Stop,
+    }
+
+    struct Context {
+        stops: u32,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum State { Stopped, Running }
+        context: Context;
+        event: Event;
+        transitions {
+            Stopped + Start => Running,
+            Running + Stop => Stopped / |ctx, _event| ctx.stops += 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generated_async_state_machine() {
+        let mut sm = StateMachine::new(Context { stops: 0 }, None);
+        sm.init(State::Stopped).await.unwrap();
+        sm.process_event(&Event::Start).await.unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::Running);
+        sm.process_event(&Event::Stop).await.unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::Stopped);
+        assert_eq!(sm.get_context().stops, 1);
+    }
+}
@@ -0,0 +1,197 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::observer::{TracingObserver, TransitionObserver};
+    use nefsm::sync::*;
+    use std::fmt::{self, Write};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum TestState {
+        Idle,
+        Running,
+        Broken,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        Start,
+        Poll,
+        Fail,
+    }
+
+    impl FsmEnum<TestState, (), TestEvent> for TestState {
+        fn create(enum_value: &TestState) -> Box<dyn Stateful<TestState, (), TestEvent> + Send> {
+            Box::new(Node {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Node {
+        id: TestState,
+    }
+
+    impl Stateful<TestState, (), TestEvent> for Node {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<TestState> {
+            match self.id {
+                TestState::Broken => Response::Error("cannot enter Broken".to_string()),
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(&mut self, event: &TestEvent, _context: &mut ()) -> Response<TestState> {
+            match event {
+                TestEvent::Start => Response::Transition(TestState::Running),
+                TestEvent::Poll => Response::Handled,
+                TestEvent::Fail => Response::Transition(TestState::Broken),
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    struct Recorder {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TransitionObserver<TestState, TestEvent> for Recorder {
+        fn before_exit(&mut self, state: &TestState) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("before_exit {:?}", state));
+        }
+
+        fn after_enter(&mut self, state: &TestState, _elapsed: Duration) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("after_enter {:?}", state));
+        }
+
+        fn on_error(&mut self, state: &TestState, error: &str) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("error {:?}: {}", state, error));
+        }
+
+        fn on_no_transition(&mut self, state: &TestState, event: &TestEvent) {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("no_transition {:?} {:?}", state, event));
+        }
+    }
+
+    #[test]
+    fn test_observer_notifications() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut sm = StateMachine::new((), None)
+            .with_observer(Box::new(Recorder { log: log.clone() }))
+            .with_observer(Box::new(TracingObserver::new()));
+        sm.init(TestState::Idle).unwrap();
+        sm.process_event(&TestEvent::Poll).unwrap();
+        sm.process_event(&TestEvent::Start).unwrap();
+        assert!(sm.process_event(&TestEvent::Fail).is_err());
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "after_enter Idle",
+                "no_transition Idle Poll",
+                "before_exit Idle",
+                "after_enter Running",
+                "before_exit Running",
+                "error Broken: cannot enter Broken",
+            ]
+        );
+    }
+
+    // Define a visitor writing the fields of a span or an event as `name=value`
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    // Define a subscriber logging the spans opened and the span each event belongs to
+    struct SpanLog {
+        next_id: AtomicU64,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Subscriber for SpanLog {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+            let mut fields = Fields(String::new());
+            span.record(&mut fields);
+            self.log.lock().unwrap().push(format!(
+                "span {} {}:{}",
+                id,
+                span.metadata().name(),
+                fields.0
+            ));
+            Id::from_u64(id)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            let parent = event.parent().map(|id| id.into_u64());
+            let message = fields.0.split(" state=").next().unwrap().to_string();
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("event in {:?}:{}", parent, message));
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_tracing_observer_opens_a_span_per_event() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let subscriber = SpanLog {
+            next_id: AtomicU64::new(0),
+            log: log.clone(),
+        };
+        tracing::subscriber::with_default(subscriber, || {
+            let mut sm =
+                StateMachine::new((), None).with_observer(Box::new(TracingObserver::new()));
+            sm.init(TestState::Idle).unwrap();
+            sm.process_event(&TestEvent::Start).unwrap();
+        });
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![
+                "event in None: message=entered state",
+                "span 1 dispatch: state=Idle event=Start",
+                "event in Some(1): message=handled event",
+                "event in Some(1): message=exiting state",
+                "event in Some(1): message=exited state",
+                "event in Some(1): message=entered state",
+            ]
+        );
+    }
+}
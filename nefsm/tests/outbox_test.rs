@@ -0,0 +1,216 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::outbox::{HasOutbox, Outbox};
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum JobState {
+        Idle,
+        Working,
+        Reporting,
+        Looping,
+    }
+
+    #[derive(Debug)]
+    enum JobEvent {
+        Start,
+        Completed,
+        Reported,
+        Spin,
+        Fail,
+    }
+
+    struct JobContext {
+        outbox: Outbox<JobEvent>,
+        log: Vec<String>,
+    }
+
+    impl HasOutbox<JobEvent> for JobContext {
+        fn outbox(&mut self) -> &mut Outbox<JobEvent> {
+            &mut self.outbox
+        }
+    }
+
+    impl FsmEnum<JobState, JobContext, JobEvent> for JobState {
+        fn create(
+            enum_value: &JobState,
+        ) -> Box<dyn Stateful<JobState, JobContext, JobEvent> + Send> {
+            Box::new(Job {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Job {
+        id: JobState,
+    }
+
+    impl Stateful<JobState, JobContext, JobEvent> for Job {
+        fn on_enter(&mut self, context: &mut JobContext) -> Response<JobState> {
+            context.log.push(format!("enter {:?}", self.id));
+            match self.id {
+                // Working finishes immediately and reports two follow-up events
+                JobState::Working => {
+                    context.outbox.post(JobEvent::Completed);
+                    context.outbox.post(JobEvent::Reported);
+                }
+                JobState::Looping => context.outbox.post(JobEvent::Spin),
+                _ => {}
+            }
+            Response::Handled
+        }
+
+        fn on_event(&mut self, event: &JobEvent, context: &mut JobContext) -> Response<JobState> {
+            context.log.push(format!("{:?} in {:?}", event, self.id));
+            match (&self.id, event) {
+                (JobState::Idle, JobEvent::Start) => Response::Transition(JobState::Working),
+                (JobState::Working, JobEvent::Completed) => {
+                    Response::Transition(JobState::Reporting)
+                }
+                (JobState::Reporting, JobEvent::Reported) => Response::Transition(JobState::Idle),
+                (JobState::Looping, JobEvent::Spin) => {
+                    context.outbox.post(JobEvent::Spin);
+                    Response::Handled
+                }
+                (_, JobEvent::Fail) => Response::Error("failed".to_string()),
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, _context: &mut JobContext) {}
+    }
+
+    fn job_context() -> JobContext {
+        JobContext {
+            outbox: Outbox::new(),
+            log: vec![],
+        }
+    }
+
+    #[test]
+    fn test_posted_events_run_to_completion() {
+        let mut sm = StateMachine::new(job_context(), None).with_event_queue(10);
+        sm.init(JobState::Idle).unwrap();
+        sm.process_event(&JobEvent::Start).unwrap();
+
+        // Both posted events are processed, in order, after the Start transition completed
+        assert_eq!(*sm.get_current_state().unwrap(), JobState::Idle);
+        assert_eq!(
+            sm.get_context().log,
+            vec![
+                "enter Idle",
+                "Start in Idle",
+                "enter Working",
+                "Completed in Working",
+                "enter Reporting",
+                "Reported in Reporting",
+                "enter Idle",
+            ]
+        );
+        assert!(sm.get_context().outbox.is_empty());
+    }
+
+    #[test]
+    fn test_failing_posted_event_is_reported() {
+        let mut sm = StateMachine::new(job_context(), None).with_event_queue(10);
+        sm.init(JobState::Idle).unwrap();
+        sm.get_context_mut().outbox.post(JobEvent::Fail);
+
+        // The failure is returned, but the events posted after it still run to completion
+        match sm.process_event(&JobEvent::Start).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidEvent(e)) => assert_eq!(e, "failed"),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("the posted Fail event should be reported"),
+        }
+        assert_eq!(*sm.get_current_state().unwrap(), JobState::Idle);
+        assert!(sm.get_context().outbox.is_empty());
+    }
+
+    #[test]
+    fn test_posted_event_loop_is_bounded() {
+        let mut sm = StateMachine::new(job_context(), None).with_event_queue(5);
+        match sm.init(JobState::Looping).map_err(Error::into_kind) {
+            Err(ErrorKind::EventLimitExceeded(limit)) => assert_eq!(limit, 5),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("the Spin loop should hit the limit"),
+        }
+        assert_eq!(sm.get_context().log.len(), 6);
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::outbox::{HasOutbox, Outbox};
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum State {
+        A,
+        B,
+        C,
+    }
+
+    #[derive(Debug)]
+    enum Event {
+        Next,
+    }
+
+    struct Context {
+        outbox: Outbox<Event>,
+    }
+
+    impl HasOutbox<Event> for Context {
+        fn outbox(&mut self) -> &mut Outbox<Event> {
+            &mut self.outbox
+        }
+    }
+
+    impl FsmEnum<State, Context, Event> for State {
+        fn create(enum_value: &State) -> Box<dyn Stateful<State, Context, Event> + Send> {
+            Box::new(Step {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Step {
+        id: State,
+    }
+
+    #[async_trait]
+    impl Stateful<State, Context, Event> for Step {
+        async fn on_enter(&mut self, context: &mut Context) -> Response<State> {
+            if self.id == State::B {
+                context.outbox.post(Event::Next);
+            }
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, _event: &Event, _context: &mut Context) -> Response<State> {
+            match self.id {
+                State::A => Response::Transition(State::B),
+                State::B => Response::Transition(State::C),
+                State::C => Response::Handled,
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut Context) {}
+    }
+
+    #[tokio::test]
+    async fn test_async_posted_events() {
+        let mut sm = StateMachine::new(
+            Context {
+                outbox: Outbox::new(),
+            },
+            None,
+        )
+        .with_event_queue(10);
+        sm.init(State::A).await.unwrap();
+        sm.process_event(&Event::Next).await.unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::C);
+    }
+}
@@ -0,0 +1,281 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug)]
+    enum DeviceEvent {
+        PowerOn,
+        Connect,
+        Update,
+        Reset,
+    }
+
+    #[derive(Default)]
+    struct Device {
+        battery: u32,
+        log: Vec<&'static str>,
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Power { Off, On }
+        context: Device;
+        event: DeviceEvent;
+        transitions {
+            Off + PowerOn => On / |ctx, _event| ctx.log.push("powered"),
+            On + Reset => Off,
+        }
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Link { Down, Up }
+        context: Device;
+        event: DeviceEvent;
+        transitions {
+            Down + Connect => Up / |ctx, _event| ctx.log.push("connected"),
+            Up + Reset => Down,
+        }
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum Firmware { Current, Updating }
+        context: Device;
+        event: DeviceEvent;
+        transitions {
+            Current + Update => Updating,
+            Updating + Reset => Current,
+        }
+        on_enter {
+            Updating => |ctx| {
+                if ctx.battery < 20 {
+                    Response::Error("battery low".to_string())
+                } else {
+                    Response::Handled
+                }
+            },
+        }
+    }
+
+    fn device(battery: u32) -> ParallelStateMachine<Device, DeviceEvent> {
+        ParallelStateMachine::new(Device {
+            battery,
+            log: vec![],
+        })
+        .with_region(Region::new(None), Power::Off)
+        .with_region(Region::new(None), Link::Down)
+        .with_region(Region::new(None), Firmware::Current)
+    }
+
+    #[test]
+    fn test_regions_share_context() {
+        let mut sm = device(50);
+        sm.init().unwrap();
+        assert_eq!(
+            sm.get_states(),
+            vec![
+                Some("Off".to_string()),
+                Some("Down".to_string()),
+                Some("Current".to_string())
+            ]
+        );
+        assert_eq!(sm.get_state::<Power>(), Some(&Power::Off));
+        assert_eq!(sm.get_state::<Link>(), Some(&Link::Down));
+        assert_eq!(sm.get_state::<Firmware>(), Some(&Firmware::Current));
+
+        sm.process_event(&DeviceEvent::PowerOn).unwrap();
+        sm.process_event(&DeviceEvent::Connect).unwrap();
+        sm.process_event(&DeviceEvent::Update).unwrap();
+        assert_eq!(sm.get_state::<Power>(), Some(&Power::On));
+        assert_eq!(sm.get_state::<Link>(), Some(&Link::Up));
+        assert_eq!(sm.get_state::<Firmware>(), Some(&Firmware::Updating));
+        assert_eq!(sm.get_context().log, vec!["powered", "connected"]);
+
+        // One event moves every region that handles it
+        sm.process_event(&DeviceEvent::Reset).unwrap();
+        assert_eq!(sm.get_state::<Power>(), Some(&Power::Off));
+        assert_eq!(sm.get_state::<Link>(), Some(&Link::Down));
+        assert_eq!(sm.get_state::<Firmware>(), Some(&Firmware::Current));
+    }
+
+    #[test]
+    fn test_failing_region_does_not_stop_the_others() {
+        let mut sm = device(10);
+        sm.init().unwrap();
+        sm.process_event(&DeviceEvent::PowerOn).unwrap();
+
+        let e = sm.process_event(&DeviceEvent::Update).unwrap_err();
+        assert_eq!(
+            e.kind(),
+            &ErrorKind::StateInvalid("battery low".to_string())
+        );
+        assert_eq!(e.get_to().map(String::as_str), Some("Updating"));
+        assert_eq!(sm.get_state::<Firmware>(), Some(&Firmware::Current));
+
+        sm.process_event(&DeviceEvent::Reset).unwrap();
+        assert_eq!(sm.get_state::<Power>(), Some(&Power::Off));
+        assert!(sm.get_region::<Link>().is_some());
+    }
+
+    #[test]
+    fn test_regions_sharing_a_state_enum() {
+        let mut sm = ParallelStateMachine::new(Device::default())
+            .with_region(Region::new(None), Link::Down)
+            .with_region(Region::new(None), Link::Up);
+        assert_eq!(sm.get_states(), vec![None, None]);
+        sm.init().unwrap();
+        assert_eq!(
+            sm.get_states(),
+            vec![Some("Down".to_string()), Some("Up".to_string())]
+        );
+        assert_eq!(sm.get_region_state::<Link>(0), Some(&Link::Down));
+        assert_eq!(sm.get_region_state::<Link>(1), Some(&Link::Up));
+        assert_eq!(sm.get_region_state::<Power>(1), None);
+        assert_eq!(sm.get_region_state::<Link>(2), None);
+
+        sm.process_event(&DeviceEvent::Connect).unwrap();
+        assert_eq!(sm.get_region_state::<Link>(0), Some(&Link::Up));
+        sm.process_event(&DeviceEvent::Reset).unwrap();
+        assert_eq!(
+            sm.get_states(),
+            vec![Some("Down".to_string()), Some("Down".to_string())]
+        );
+        assert_eq!(sm.get_context().log, vec!["connected"]);
+    }
+
+    #[test]
+    fn test_region_runs_against_a_borrowed_context() {
+        let mut device = Device {
+            battery: 50,
+            log: vec![],
+        };
+        let mut power = Region::new(None);
+        power.init(&mut device, Power::Off).unwrap();
+        power
+            .process_event(&mut device, &DeviceEvent::PowerOn)
+            .unwrap();
+        assert_eq!(power.get_current_state(), Some(&Power::On));
+        assert_eq!(device.log, vec!["powered"]);
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::Async::*;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Waker};
+
+    #[derive(Debug)]
+    enum Event {
+        Toggle,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum Lamp { Dark, Lit }
+        context: u32;
+        event: Event;
+        transitions {
+            Dark + Toggle => Lit / |ctx, _event| *ctx += 1,
+            Lit + Toggle => Dark,
+        }
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum Fan { Stopped, Spinning }
+        context: u32;
+        event: Event;
+        transitions {
+            Stopped + Toggle => Spinning / |ctx, _event| *ctx += 10,
+            Spinning + Toggle => Stopped,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_regions() {
+        let mut sm = ParallelStateMachine::new(0)
+            .with_region(Region::new(None), Lamp::Dark)
+            .with_region(Region::new(None), Fan::Stopped);
+        // The machine and its futures are Send, so it can run in a task of its own
+        let sm = tokio::spawn(async move {
+            sm.init().await.unwrap();
+            sm.process_event(&Event::Toggle).await.unwrap();
+            sm
+        })
+        .await
+        .unwrap();
+        assert_eq!(sm.get_state::<Lamp>(), Some(&Lamp::Lit));
+        assert_eq!(sm.get_region_state::<Fan>(1), Some(&Fan::Spinning));
+        assert_eq!(
+            sm.get_states(),
+            vec![Some("Lit".to_string()), Some("Spinning".to_string())]
+        );
+        assert_eq!(*sm.get_context(), 11);
+    }
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum Relay {
+        Open,
+        Closed,
+    }
+
+    impl FsmEnum<Relay, u32, Event> for Relay {
+        fn create(enum_value: &Relay) -> Box<dyn Stateful<Relay, u32, Event> + Send> {
+            Box::new(SlowRelay {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    // Define a state that waits once before switching, like a relay that takes time to settle
+    struct SlowRelay {
+        id: Relay,
+    }
+
+    #[async_trait]
+    impl Stateful<Relay, u32, Event> for SlowRelay {
+        async fn on_enter(&mut self, _context: &mut u32) -> Response<Relay> {
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, _event: &Event, context: &mut u32) -> Response<Relay> {
+            tokio::task::yield_now().await;
+            *context += 100;
+            match self.id {
+                Relay::Open => Response::Transition(Relay::Closed),
+                Relay::Closed => Response::Transition(Relay::Open),
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut u32) {}
+    }
+
+    #[tokio::test]
+    async fn test_dropped_event_keeps_the_context() {
+        let mut sm = ParallelStateMachine::new(0)
+            .with_region(Region::new(None), Lamp::Dark)
+            .with_region(Region::new(None), Relay::Open);
+        sm.init().await.unwrap();
+
+        // The lamp handles the event, then the call is dropped while the relay is waiting
+        {
+            let future = pin!(sm.process_event(&Event::Toggle));
+            assert!(future
+                .poll(&mut Context::from_waker(Waker::noop()))
+                .is_pending());
+        }
+        assert_eq!(*sm.get_context(), 1);
+        assert_eq!(sm.get_state::<Lamp>(), Some(&Lamp::Lit));
+        assert_eq!(sm.get_state::<Relay>(), Some(&Relay::Open));
+
+        sm.process_event(&Event::Toggle).await.unwrap();
+        assert_eq!(*sm.get_context(), 101);
+        assert_eq!(sm.get_state::<Relay>(), Some(&Relay::Closed));
+    }
+}
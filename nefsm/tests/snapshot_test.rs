@@ -0,0 +1,124 @@
+#![cfg(feature = "serde")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::snapshot::Snapshot;
+    use nefsm::sync::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone, Serialize, Deserialize)]
+    enum TestState {
+        Idle,
+        Counting,
+    }
+
+    #[derive(Debug)]
+    enum TestEvent {
+        Start,
+        Tick,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct TestContext {
+        enters: u32,
+    }
+
+    impl FsmEnum<TestState, TestContext, TestEvent> for TestState {
+        fn create(
+            enum_value: &TestState,
+        ) -> Box<dyn Stateful<TestState, TestContext, TestEvent> + Send> {
+            match enum_value {
+                TestState::Idle => Box::new(Idle {}),
+                TestState::Counting => Box::new(Counting { ticks: 0 }),
+            }
+        }
+    }
+
+    struct Idle {}
+
+    impl Stateful<TestState, TestContext, TestEvent> for Idle {
+        fn on_enter(&mut self, context: &mut TestContext) -> Response<TestState> {
+            context.enters += 1;
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            _event: &TestEvent,
+            _context: &mut TestContext,
+        ) -> Response<TestState> {
+            Response::Transition(TestState::Counting)
+        }
+
+        fn on_exit(&mut self, _context: &mut TestContext) {}
+    }
+
+    struct Counting {
+        ticks: u32,
+    }
+
+    impl Stateful<TestState, TestContext, TestEvent> for Counting {
+        fn on_enter(&mut self, context: &mut TestContext) -> Response<TestState> {
+            context.enters += 1;
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            _event: &TestEvent,
+            _context: &mut TestContext,
+        ) -> Response<TestState> {
+            self.ticks += 1;
+            Response::Handled
+        }
+
+        fn on_exit(&mut self, _context: &mut TestContext) {}
+
+        fn save_data(&self) -> Option<Vec<u8>> {
+            Some(self.ticks.to_le_bytes().to_vec())
+        }
+
+        fn restore_data(&mut self, data: &[u8]) {
+            self.ticks = u32::from_le_bytes(data.try_into().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut sm = StateMachine::new(TestContext { enters: 0 }, None);
+        sm.init(TestState::Idle).unwrap();
+        sm.process_event(&TestEvent::Start).unwrap();
+        sm.process_event(&TestEvent::Tick).unwrap();
+        sm.process_event(&TestEvent::Tick).unwrap();
+
+        let json = serde_json::to_string(&sm.snapshot().unwrap()).unwrap();
+        let snapshot: Snapshot<TestState, TestContext> = serde_json::from_str(&json).unwrap();
+        assert_eq!(snapshot.current_state, TestState::Counting);
+        assert_eq!(
+            snapshot.state_data,
+            vec![(TestState::Counting, vec![2, 0, 0, 0])]
+        );
+
+        // Restoring does not run on_enter again
+        let mut restored =
+            StateMachine::<TestState, TestContext, TestEvent>::restore(snapshot, None);
+        assert_eq!(*restored.get_current_state().unwrap(), TestState::Counting);
+        assert_eq!(restored.get_context().enters, 2);
+
+        restored.process_event(&TestEvent::Tick).unwrap();
+        assert_eq!(
+            restored.snapshot().unwrap().state_data,
+            vec![(TestState::Counting, vec![3, 0, 0, 0])]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_requires_init() {
+        let sm =
+            StateMachine::<TestState, TestContext, TestEvent>::new(TestContext { enters: 0 }, None);
+        assert!(matches!(
+            sm.snapshot().map_err(Error::into_kind),
+            Err(ErrorKind::StateMachineNotInitialized)
+        ));
+    }
+}
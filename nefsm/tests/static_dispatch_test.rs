@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use nefsm::error::{Error, ErrorKind};
+    use nefsm::response::Response;
+    use nefsm::static_dispatch::{StateEnum, StaticStateMachine};
+
+    #[derive(Debug, PartialEq)]
+    enum Login {
+        LoggedOut,
+        Typing { attempts: u32 },
+        LoggedIn { user: String },
+        Locked,
+    }
+
+    #[derive(Debug)]
+    enum LoginEvent {
+        Start,
+        Password(&'static str),
+        Logout,
+    }
+
+    #[derive(Default)]
+    struct Audit {
+        log: Vec<String>,
+    }
+
+    impl StateEnum<Audit, LoginEvent> for Login {
+        fn on_enter(&mut self, context: &mut Audit) -> Response<Login> {
+            context.log.push(format!("enter {:?}", self));
+            match self {
+                Login::Typing { attempts } if *attempts >= 3 => Response::Transition(Login::Locked),
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(&mut self, event: &LoginEvent, _context: &mut Audit) -> Response<Login> {
+            match (self, event) {
+                (Login::LoggedOut, LoginEvent::Start) => {
+                    Response::Transition(Login::Typing { attempts: 0 })
+                }
+                (Login::Typing { .. }, LoginEvent::Password("secret")) => {
+                    Response::Transition(Login::LoggedIn {
+                        user: "alice".to_string(),
+                    })
+                }
+                (Login::Typing { attempts }, LoginEvent::Password(_)) => {
+                    Response::Transition(Login::Typing {
+                        attempts: *attempts + 1,
+                    })
+                }
+                (Login::LoggedIn { .. }, LoginEvent::Logout) => {
+                    Response::Transition(Login::LoggedOut)
+                }
+                (Login::Locked, _) => Response::Error("account locked"),
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, context: &mut Audit) {
+            context.log.push(format!("exit {:?}", self));
+        }
+    }
+
+    #[test]
+    fn test_states_carry_their_data() {
+        let mut sm = StaticStateMachine::new(Audit::default());
+        assert!(matches!(
+            sm.process_event(&LoginEvent::Start)
+                .map_err(Error::into_kind),
+            Err(ErrorKind::StateMachineNotInitialized)
+        ));
+        sm.init(Login::LoggedOut).unwrap();
+        sm.process_event(&LoginEvent::Start).unwrap();
+        sm.process_event(&LoginEvent::Password("guess")).unwrap();
+        assert_eq!(sm.get_current_state(), Some(&Login::Typing { attempts: 1 }));
+
+        sm.process_event(&LoginEvent::Logout).unwrap();
+        sm.process_event(&LoginEvent::Password("secret")).unwrap();
+        assert_eq!(
+            sm.get_current_state(),
+            Some(&Login::LoggedIn {
+                user: "alice".to_string()
+            })
+        );
+
+        let (audit, state) = sm.into_parts();
+        assert_eq!(
+            state,
+            Some(Login::LoggedIn {
+                user: "alice".to_string()
+            })
+        );
+        assert_eq!(
+            audit.log,
+            vec![
+                "enter LoggedOut",
+                "exit LoggedOut",
+                "enter Typing { attempts: 0 }",
+                "exit Typing { attempts: 0 }",
+                "enter Typing { attempts: 1 }",
+                "exit Typing { attempts: 1 }",
+                "enter LoggedIn { user: \"alice\" }",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_on_enter_transition_and_errors() {
+        let mut sm = StaticStateMachine::new(Audit::default());
+        sm.init(Login::Typing { attempts: 2 }).unwrap();
+        sm.process_event(&LoginEvent::Password("guess")).unwrap();
+        assert_eq!(sm.get_current_state(), Some(&Login::Locked));
+        assert!(matches!(
+            sm.process_event(&LoginEvent::Logout).map_err(Error::into_kind),
+            Err(ErrorKind::InvalidEvent(e)) if e == "account locked"
+        ));
+        assert_eq!(sm.get_current_state(), Some(&Login::Locked));
+    }
+
+    #[test]
+    fn test_reinit() {
+        let mut sm = StaticStateMachine::new(Audit::default());
+        sm.init(Login::Typing { attempts: 1 }).unwrap();
+        sm.reinit(Login::LoggedOut).unwrap();
+        assert_eq!(sm.get_current_state(), Some(&Login::LoggedOut));
+        sm.reset();
+        assert!(sm.get_current_state().is_none());
+        assert_eq!(
+            sm.get_context().log,
+            vec![
+                "enter Typing { attempts: 1 }",
+                "exit Typing { attempts: 1 }",
+                "enter LoggedOut",
+                "exit LoggedOut",
+            ]
+        );
+    }
+
+    enum Spin {
+        Again(u32),
+    }
+
+    impl StateEnum<(), ()> for Spin {
+        fn on_enter(&mut self, _context: &mut ()) -> Response<Spin> {
+            let Spin::Again(n) = self;
+            Response::Transition(Spin::Again(*n + 1))
+        }
+
+        fn on_event(&mut self, _event: &(), _context: &mut ()) -> Response<Spin> {
+            Response::Handled
+        }
+
+        fn on_exit(&mut self, _context: &mut ()) {}
+    }
+
+    #[test]
+    fn test_enter_chain_limit() {
+        let mut sm = StaticStateMachine::new(()).with_max_enter_chain(3);
+        match sm.init(Spin::Again(0)).map_err(Error::into_kind) {
+            Err(ErrorKind::EnterChainLimitExceeded { last, length }) => {
+                assert!(matches!(last, Spin::Again(4)));
+                assert_eq!(length, 5);
+            }
+            _ => panic!("the chain should be too long"),
+        }
+        assert!(sm.get_current_state().is_none());
+    }
+}
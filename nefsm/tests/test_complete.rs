@@ -1,4 +1,6 @@
-use nefsm::sync::{Error, FsmEnum, Response, StateMachine, Stateful};
+#![cfg(feature = "std")]
+
+use nefsm::sync::{FsmEnum, Response, StateMachine, Stateful};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum TestState {
@@ -0,0 +1,149 @@
+#![cfg(feature = "testing")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+    use nefsm::testing::Scenario;
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Dial,
+        Answer,
+        Reject,
+        HangUp,
+    }
+
+    struct CallContext {
+        retries: u32,
+    }
+
+    nefsm::fsm! {
+        sync;
+        enum CallState { Idle, Dialing, Connected }
+        context: CallContext;
+        event: CallEvent;
+        transitions {
+            Idle + Dial => Dialing,
+            Dialing + Answer => Connected,
+            Dialing + Reject => Idle,
+            Connected + HangUp => Idle,
+        }
+        on_enter {
+            Dialing => |ctx| {
+                ctx.retries += 1;
+                if ctx.retries > 2 {
+                    Response::Error("too many retries".to_string())
+                } else {
+                    Response::Handled
+                }
+            },
+        }
+    }
+
+    fn machine() -> StateMachine<CallState, CallContext, CallEvent> {
+        StateMachine::new(CallContext { retries: 0 }, None)
+    }
+
+    #[test]
+    fn test_scenario() {
+        let sm = Scenario::new(CallState::Idle)
+            .expect_state(CallState::Idle)
+            .event(CallEvent::Dial)
+            .expect_state(CallState::Dialing)
+            .event(CallEvent::Reject)
+            .event(CallEvent::Dial)
+            .event(CallEvent::Answer)
+            .expect_state(CallState::Connected)
+            .expect_context("two dial attempts", |ctx: &CallContext| ctx.retries == 2)
+            .event(CallEvent::HangUp)
+            .event(CallEvent::Dial)
+            .expect_error(|kind| matches!(kind, ErrorKind::StateInvalid(_)))
+            .expect_state(CallState::Idle)
+            .run(machine());
+        assert_eq!(sm.get_context().retries, 3);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "step 3 failed: expected state Connected, but the machine is in Some(Dialing)"
+    )]
+    fn test_failed_state_expectation() {
+        Scenario::new(CallState::Idle)
+            .event(CallEvent::Dial)
+            .event(CallEvent::HangUp)
+            .expect_state(CallState::Connected)
+            .run(machine());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "step 6 failed: the previous step failed: state cannot be entered: too many retries"
+    )]
+    fn test_unexpected_error() {
+        Scenario::new(CallState::Idle)
+            .event(CallEvent::Dial)
+            .event(CallEvent::Reject)
+            .event(CallEvent::Dial)
+            .event(CallEvent::Reject)
+            .event(CallEvent::Dial)
+            .event(CallEvent::Dial)
+            .run(machine());
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "trace:\ninit Idle\n  enter Idle\n  -> now in Some(Idle)\nevent Dial\n  Idle handled Dial\n  exit Idle\n  enter Dialing"
+    )]
+    fn test_failure_prints_trace() {
+        Scenario::new(CallState::Idle)
+            .event(CallEvent::Dial)
+            .expect_context("no retries", |ctx: &CallContext| ctx.retries == 0)
+            .run(machine());
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use nefsm::testing::Scenario;
+    use nefsm::Async::*;
+
+    #[derive(Debug)]
+    enum DoorEvent {
+        Open,
+        Close,
+    }
+
+    nefsm::fsm! {
+        Async;
+        enum DoorState { Closed, Opened }
+        context: u32;
+        event: DoorEvent;
+        transitions {
+            Closed + Open => Opened / |ctx, _event| *ctx += 1,
+            Opened + Close => Closed,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_scenario() {
+        let sm = Scenario::new(DoorState::Closed)
+            .event(DoorEvent::Open)
+            .expect_state(DoorState::Opened)
+            .event(DoorEvent::Close)
+            .event(DoorEvent::Open)
+            .expect_context("opened twice", |ctx: &u32| *ctx == 2)
+            .run_async(StateMachine::new(0, None))
+            .await;
+        assert_eq!(*sm.get_current_state().unwrap(), DoorState::Opened);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected an error, but the last step succeeded")]
+    async fn test_async_missing_error() {
+        Scenario::new(DoorState::Closed)
+            .event(DoorEvent::Close)
+            .expect_error(|_| true)
+            .run_async(StateMachine::new(0, None))
+            .await;
+    }
+}
@@ -0,0 +1,218 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use nefsm::timer::{Clock, ManualClock, SystemClock, Timers};
+    use nefsm::Async::*;
+    use std::sync::Arc;
+    use std::task::{Context, Waker};
+    use std::time::Duration;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CallState {
+        Idle,
+        Dialing,
+        Connected,
+    }
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Dial,
+        Answer,
+        Timeout,
+    }
+
+    struct CallContext {
+        timeout: Duration,
+    }
+
+    impl FsmEnum<CallState, CallContext, CallEvent> for CallState {
+        fn create(
+            enum_value: &CallState,
+        ) -> Box<dyn Stateful<CallState, CallContext, CallEvent> + Send> {
+            Box::new(Call {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Call {
+        id: CallState,
+    }
+
+    #[async_trait]
+    impl Stateful<CallState, CallContext, CallEvent> for Call {
+        async fn on_enter(&mut self, _context: &mut CallContext) -> Response<CallState> {
+            Response::Handled
+        }
+
+        async fn on_event(
+            &mut self,
+            event: &CallEvent,
+            _context: &mut CallContext,
+        ) -> Response<CallState> {
+            match (&self.id, event) {
+                (CallState::Idle, CallEvent::Dial) => Response::Transition(CallState::Dialing),
+                (CallState::Dialing, CallEvent::Answer) => {
+                    Response::Transition(CallState::Connected)
+                }
+                (CallState::Dialing, CallEvent::Timeout) => Response::Transition(CallState::Idle),
+                _ => Response::Error(format!("{:?} is not valid in {:?}", event, self.id)),
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut CallContext) {}
+
+        fn schedule_timers(&mut self, context: &CallContext, timers: &mut Timers<CallEvent>) {
+            if self.id == CallState::Dialing {
+                timers.after(context.timeout, CallEvent::Timeout);
+            }
+        }
+    }
+
+    fn call_machine(
+        timeout: Duration,
+        clock: &ManualClock,
+    ) -> StateMachine<CallState, CallContext, CallEvent> {
+        StateMachine::new(CallContext { timeout }, None).with_clock(Arc::new(clock.clone()))
+    }
+
+    #[tokio::test]
+    async fn test_timer_fires_after_delay() {
+        let clock = ManualClock::new();
+        let mut sm = call_machine(Duration::from_secs(30), &clock);
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+        assert_eq!(sm.pending_timers(), 1);
+
+        clock.advance(Duration::from_secs(29));
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 0);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Dialing);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 1);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+        assert_eq!(sm.pending_timers(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_timer_cancelled_on_exit() {
+        let clock = ManualClock::new();
+        let mut sm = call_machine(Duration::from_secs(30), &clock);
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+        sm.process_event(&CallEvent::Answer).await.unwrap();
+        assert_eq!(sm.pending_timers(), 0);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 0);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_next_timer_waits_for_deadline() {
+        let clock = ManualClock::new();
+        let mut sm = call_machine(Duration::from_secs(30), &clock);
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+
+        // Waiting on a manual clock does not move it; the timer is due once the clock is advanced
+        let mut next_timer = sm.next_timer();
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(next_timer.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(clock.now(), Duration::ZERO);
+        drop(sm.next_timer());
+        assert_eq!(clock.now(), Duration::ZERO);
+
+        let waiting = tokio::spawn(next_timer);
+        clock.advance(Duration::from_secs(29));
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        waiting.await.unwrap();
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 1);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tokio_clock_timer() {
+        let mut sm = StateMachine::new(
+            CallContext {
+                timeout: Duration::from_millis(10),
+            },
+            None,
+        )
+        .with_clock(Arc::new(nefsm::timer::TokioClock::new()));
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+
+        sm.next_timer().await;
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 1);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+    }
+
+    #[tokio::test]
+    async fn test_system_clock_timer() {
+        let mut sm = StateMachine::new(
+            CallContext {
+                timeout: Duration::from_millis(10),
+            },
+            None,
+        )
+        .with_clock(Arc::new(SystemClock::new()));
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+
+        sm.next_timer().await;
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 1);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+    }
+
+    // Define a helper counting the threads of this process
+    #[cfg(target_os = "linux")]
+    fn thread_count() -> usize {
+        std::fs::read_to_string("/proc/self/status")
+            .unwrap()
+            .lines()
+            .find_map(|line| line.strip_prefix("Threads:"))
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_system_clock_sleeps_share_one_thread() {
+        let clock = SystemClock::new();
+        #[cfg(target_os = "linux")]
+        let threads = thread_count();
+
+        // Poll long sleeps once, so they wait on the timer thread, then drop them
+        for _ in 0..200 {
+            let mut sleep = clock.sleep(Duration::from_secs(30));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(sleep.as_mut().poll(&mut cx).is_pending());
+        }
+        #[cfg(target_os = "linux")]
+        assert!(thread_count() < threads + 20);
+
+        // Sleeps complete in the order of their deadlines, whatever order they were started in
+        let started = clock.now();
+        let order = Arc::new(std::sync::Mutex::new(vec![]));
+        let sleeps = [30, 10, 20].map(|ms| {
+            let sleep = clock.sleep(Duration::from_millis(ms));
+            let order = order.clone();
+            tokio::spawn(async move {
+                sleep.await;
+                order.lock().unwrap().push(ms);
+            })
+        });
+        for sleep in sleeps {
+            sleep.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![10, 20, 30]);
+        assert!(clock.now() - started >= Duration::from_millis(30));
+    }
+}
@@ -0,0 +1,222 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum AccountState {
+        Open,
+        Closed,
+    }
+
+    #[derive(Debug)]
+    enum AccountEvent {
+        Withdraw(i32),
+        Close,
+    }
+
+    #[derive(Clone)]
+    struct Ledger {
+        balance: i32,
+        log: Vec<String>,
+    }
+
+    impl FsmEnum<AccountState, Ledger, AccountEvent> for AccountState {
+        fn create(
+            enum_value: &AccountState,
+        ) -> Box<dyn Stateful<AccountState, Ledger, AccountEvent> + Send> {
+            Box::new(Account {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Account {
+        id: AccountState,
+    }
+
+    impl Stateful<AccountState, Ledger, AccountEvent> for Account {
+        fn on_enter(&mut self, context: &mut Ledger) -> Response<AccountState> {
+            context.log.push(format!("enter {:?}", self.id));
+            match self.id {
+                AccountState::Closed if context.balance != 0 => {
+                    Response::Error("balance must be zero".to_string())
+                }
+                _ => Response::Handled,
+            }
+        }
+
+        fn on_event(
+            &mut self,
+            event: &AccountEvent,
+            context: &mut Ledger,
+        ) -> Response<AccountState> {
+            match (&self.id, event) {
+                (AccountState::Open, AccountEvent::Withdraw(amount)) => {
+                    context.balance -= amount;
+                    if context.balance < 0 {
+                        Response::Error("overdrawn".to_string())
+                    } else {
+                        Response::Handled
+                    }
+                }
+                (AccountState::Open, AccountEvent::Close) => {
+                    Response::Transition(AccountState::Closed)
+                }
+                _ => Response::Unhandled,
+            }
+        }
+
+        fn on_exit(&mut self, context: &mut Ledger) {
+            context.log.push(format!("exit {:?}", self.id));
+        }
+    }
+
+    fn ledger(balance: i32) -> Ledger {
+        Ledger {
+            balance,
+            log: vec![],
+        }
+    }
+
+    #[test]
+    fn test_failed_event_is_rolled_back() {
+        let mut sm = StateMachine::new(ledger(100), None).with_transactions();
+        sm.init(AccountState::Open).unwrap();
+
+        assert!(sm.process_event(&AccountEvent::Withdraw(150)).is_err());
+        assert_eq!(sm.get_context().balance, 100);
+
+        // on_exit of Open ran before Closed rejected the entry, and both are undone
+        assert!(sm.process_event(&AccountEvent::Close).is_err());
+        assert_eq!(*sm.get_current_state().unwrap(), AccountState::Open);
+        assert_eq!(sm.get_context().log, vec!["enter Open"]);
+
+        sm.process_event(&AccountEvent::Withdraw(100)).unwrap();
+        sm.process_event(&AccountEvent::Close).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), AccountState::Closed);
+        assert_eq!(
+            sm.get_context().log,
+            vec!["enter Open", "exit Open", "enter Closed"]
+        );
+    }
+
+    #[test]
+    fn test_without_transactions_side_effects_remain() {
+        let mut sm = StateMachine::new(ledger(100), None);
+        sm.init(AccountState::Open).unwrap();
+
+        assert!(sm.process_event(&AccountEvent::Close).is_err());
+        assert_eq!(*sm.get_current_state().unwrap(), AccountState::Open);
+        assert_eq!(
+            sm.get_context().log,
+            vec!["enter Open", "exit Open", "enter Closed"]
+        );
+    }
+
+    #[test]
+    fn test_failed_init_is_rolled_back() {
+        let mut sm = StateMachine::new(ledger(100), None).with_transactions();
+        assert!(sm.init(AccountState::Closed).is_err());
+        assert_eq!(sm.get_current_state(), None);
+        assert!(sm.get_context().log.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::timer::{ManualClock, Timers};
+    use nefsm::Async::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum CallState {
+        Idle,
+        Dialing,
+        Connected,
+    }
+
+    #[derive(Debug)]
+    enum CallEvent {
+        Dial,
+        Answer,
+        Timeout,
+    }
+
+    #[derive(Clone)]
+    struct CallContext {
+        lines_free: u32,
+    }
+
+    impl FsmEnum<CallState, CallContext, CallEvent> for CallState {
+        fn create(
+            enum_value: &CallState,
+        ) -> Box<dyn Stateful<CallState, CallContext, CallEvent> + Send> {
+            Box::new(Call {
+                id: enum_value.clone(),
+            })
+        }
+    }
+
+    struct Call {
+        id: CallState,
+    }
+
+    #[async_trait]
+    impl Stateful<CallState, CallContext, CallEvent> for Call {
+        async fn on_enter(&mut self, context: &mut CallContext) -> Response<CallState> {
+            if self.id == CallState::Connected {
+                if context.lines_free == 0 {
+                    return Response::Error("no free line".to_string());
+                }
+                context.lines_free -= 1;
+            }
+            Response::Handled
+        }
+
+        async fn on_event(
+            &mut self,
+            event: &CallEvent,
+            _context: &mut CallContext,
+        ) -> Response<CallState> {
+            match (&self.id, event) {
+                (CallState::Idle, CallEvent::Dial) => Response::Transition(CallState::Dialing),
+                (CallState::Dialing, CallEvent::Answer) => {
+                    Response::Transition(CallState::Connected)
+                }
+                (CallState::Dialing, CallEvent::Timeout) => Response::Transition(CallState::Idle),
+                _ => Response::Unhandled,
+            }
+        }
+
+        async fn on_exit(&mut self, _context: &mut CallContext) {}
+
+        fn schedule_timers(&mut self, _context: &CallContext, timers: &mut Timers<CallEvent>) {
+            if self.id == CallState::Dialing {
+                timers.after(Duration::from_secs(30), CallEvent::Timeout);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_cancelled_timers() {
+        let clock = ManualClock::new();
+        let mut sm = StateMachine::new(CallContext { lines_free: 0 }, None)
+            .with_clock(Arc::new(clock.clone()))
+            .with_transactions();
+        sm.init(CallState::Idle).await.unwrap();
+        sm.process_event(&CallEvent::Dial).await.unwrap();
+
+        // Leaving Dialing cancelled its timeout, which comes back when entering Connected fails
+        assert!(sm.process_event(&CallEvent::Answer).await.is_err());
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Dialing);
+        assert_eq!(sm.pending_timers(), 1);
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(sm.fire_due_timers().await.unwrap(), 1);
+        assert_eq!(*sm.get_current_state().unwrap(), CallState::Idle);
+    }
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "std")]
+
+#[cfg(test)]
+mod tests {
+    use nefsm::sync::*;
+    use nefsm::table::{Transition, TransitionTable};
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum DoorState {
+        Closed,
+        Open,
+        Locked,
+    }
+
+    #[derive(Debug)]
+    enum DoorEvent {
+        Open,
+        Close,
+        Lock(u32),
+        Unlock(u32),
+    }
+
+    struct DoorContext {
+        code: Option<u32>,
+        exits: u32,
+    }
+
+    impl FsmEnum<DoorState, DoorContext, DoorEvent> for DoorState {
+        fn create(
+            _enum_value: &DoorState,
+        ) -> Box<dyn Stateful<DoorState, DoorContext, DoorEvent> + Send> {
+            Box::new(Door {})
+        }
+    }
+
+    struct Door {}
+
+    impl Stateful<DoorState, DoorContext, DoorEvent> for Door {
+        fn on_enter(&mut self, _context: &mut DoorContext) -> Response<DoorState> {
+            Response::Handled
+        }
+
+        fn on_event(
+            &mut self,
+            _event: &DoorEvent,
+            _context: &mut DoorContext,
+        ) -> Response<DoorState> {
+            Response::Error("not in the transition table".to_string())
+        }
+
+        fn on_exit(&mut self, context: &mut DoorContext) {
+            context.exits += 1;
+        }
+    }
+
+    fn door_table() -> TransitionTable<DoorState, DoorContext, DoorEvent> {
+        TransitionTable::new()
+            .add_transition(Transition::new(
+                DoorState::Closed,
+                &DoorEvent::Open,
+                DoorState::Open,
+            ))
+            .add_transition(Transition::new(
+                DoorState::Open,
+                &DoorEvent::Close,
+                DoorState::Closed,
+            ))
+            .add_transition(
+                Transition::new(DoorState::Closed, &DoorEvent::Lock(0), DoorState::Locked).action(
+                    |ctx: &mut DoorContext, event| {
+                        if let DoorEvent::Lock(code) = event {
+                            ctx.code = Some(*code);
+                        }
+                    },
+                ),
+            )
+            .add_transition(
+                Transition::new(DoorState::Locked, &DoorEvent::Unlock(0), DoorState::Closed)
+                    .guard(|ctx: &DoorContext, event| match event {
+                        DoorEvent::Unlock(code) => ctx.code == Some(*code),
+                        _ => false,
+                    })
+                    .action(|ctx: &mut DoorContext, _event| ctx.code = None),
+            )
+    }
+
+    #[test]
+    fn test_table_guards_and_actions() {
+        let mut sm = StateMachine::new(
+            DoorContext {
+                code: None,
+                exits: 0,
+            },
+            None,
+        )
+        .with_transitions(door_table());
+        sm.init(DoorState::Closed).unwrap();
+
+        sm.process_event(&DoorEvent::Lock(1234)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), DoorState::Locked);
+        assert_eq!(sm.get_context().code, Some(1234));
+
+        // A rejected guard leaves the machine untouched and on_exit is never called
+        match sm.process_event(&DoorEvent::Unlock(1)) {
+            Err(e) => {
+                assert_eq!(e.kind(), &ErrorKind::GuardRejected);
+                assert_eq!(e.get_event(), Some("Unlock(1)"));
+            }
+            Ok(_) => panic!("guard should reject the wrong code"),
+        }
+        assert_eq!(*sm.get_current_state().unwrap(), DoorState::Locked);
+        assert_eq!(sm.get_context().exits, 1);
+
+        sm.process_event(&DoorEvent::Unlock(1234)).unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), DoorState::Closed);
+        assert_eq!(sm.get_context().code, None);
+        assert_eq!(sm.get_context().exits, 2);
+
+        // Events without an edge fall through to on_event
+        match sm.process_event(&DoorEvent::Close).map_err(Error::into_kind) {
+            Err(ErrorKind::InvalidEvent(e)) => assert_eq!(e, "not in the transition table"),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("Close is not valid in Closed"),
+        }
+    }
+
+    #[test]
+    fn test_table_is_inspectable() {
+        let table = door_table();
+        assert_eq!(table.get_transitions().len(), 4);
+
+        let unlock = table
+            .find(&DoorState::Locked, &DoorEvent::Unlock(7))
+            .next()
+            .unwrap();
+        assert_eq!(unlock.get_event_name(), "Unlock");
+        assert_eq!(*unlock.get_to(), DoorState::Closed);
+        assert!(unlock.has_guard());
+
+        let context = DoorContext {
+            code: Some(7),
+            exits: 0,
+        };
+        assert!(unlock.is_allowed(&context, &DoorEvent::Unlock(7)));
+        assert!(!unlock.is_allowed(&context, &DoorEvent::Unlock(8)));
+        assert!(table
+            .find(&DoorState::Open, &DoorEvent::Open)
+            .next()
+            .is_none());
+    }
+}
+
+#[cfg(test)]
+mod async_tests {
+    use async_trait::async_trait;
+    use nefsm::table::{Transition, TransitionTable};
+    use nefsm::Async::*;
+
+    #[derive(Debug, Hash, PartialEq, Eq, Clone)]
+    enum State {
+        Idle,
+        Running,
+    }
+
+    #[derive(Debug)]
+    enum Event {
+        Start,
+    }
+
+    impl FsmEnum<State, u32, Event> for State {
+        fn create(_enum_value: &State) -> Box<dyn Stateful<State, u32, Event> + Send> {
+            Box::new(Node {})
+        }
+    }
+
+    struct Node {}
+
+    #[async_trait]
+    impl Stateful<State, u32, Event> for Node {
+        async fn on_enter(&mut self, _context: &mut u32) -> Response<State> {
+            Response::Handled
+        }
+
+        async fn on_event(&mut self, _event: &Event, _context: &mut u32) -> Response<State> {
+            Response::Unhandled
+        }
+
+        async fn on_exit(&mut self, _context: &mut u32) {}
+    }
+
+    fn budget_table() -> TransitionTable<State, u32, Event> {
+        TransitionTable::new().add_transition(
+            Transition::new(State::Idle, &Event::Start, State::Running)
+                .guard(|budget: &u32, _event| *budget > 0)
+                .action(|budget: &mut u32, _event| *budget -= 1),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_async_table_guard() {
+        let mut sm = StateMachine::new(1, None).with_transitions(budget_table());
+        sm.init(State::Idle).await.unwrap();
+        sm.process_event(&Event::Start).await.unwrap();
+        assert_eq!(*sm.get_current_state().unwrap(), State::Running);
+        assert_eq!(*sm.get_context(), 0);
+
+        let mut sm = StateMachine::new(0, None).with_transitions(budget_table());
+        sm.init(State::Idle).await.unwrap();
+        assert!(matches!(
+            sm.process_event(&Event::Start).await.map_err(Error::into_kind),
+            Err(ErrorKind::GuardRejected)
+        ));
+        assert_eq!(*sm.get_current_state().unwrap(), State::Idle);
+    }
+}
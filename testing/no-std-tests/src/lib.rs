@@ -0,0 +1,284 @@
+//! A UART packet framer and a button debouncer built on `nefsm` without its `std` feature, so that the
+//! crate compiles for targets such as `thumbv7em-none-eabihf` with only `core`. The unit tests run on
+//! the host, and `tests/thumbv7em.rs` builds the crate for the embedded target.
+
+#![cfg_attr(not(test), no_std)]
+
+use nefsm::error::{Error, ErrorKind};
+use nefsm::fixed::{self, FsmEnum, Stateful};
+use nefsm::response::Response;
+use nefsm::static_dispatch::{StateEnum, StaticStateMachine};
+
+pub const SYNC: u8 = 0x7E;
+pub const MAX_PAYLOAD: usize = 8;
+
+// Define the error type returned by the framer states
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    TooLong(u8),
+    BadChecksum,
+}
+
+// Define the framer states; the payload being received is stored in the state itself
+#[derive(Debug, PartialEq)]
+pub enum Framer {
+    WaitSync,
+    Length,
+    Payload {
+        buffer: [u8; MAX_PAYLOAD],
+        length: u8,
+        received: u8,
+    },
+    Checksum {
+        buffer: [u8; MAX_PAYLOAD],
+        length: u8,
+    },
+}
+
+// Define the context, which counts the packets received and keeps the last one
+#[derive(Debug, Default)]
+pub struct Link {
+    pub packets: u32,
+    pub last: [u8; MAX_PAYLOAD],
+    pub last_length: u8,
+}
+
+impl StateEnum<Link, u8, FrameError> for Framer {
+    fn on_enter(&mut self, _link: &mut Link) -> Response<Framer, FrameError> {
+        match *self {
+            Framer::Payload {
+                buffer, length: 0, ..
+            } => Response::Transition(Framer::Checksum { buffer, length: 0 }),
+            _ => Response::Handled,
+        }
+    }
+
+    fn on_event(&mut self, byte: &u8, link: &mut Link) -> Response<Framer, FrameError> {
+        let byte = *byte;
+        match self {
+            Framer::WaitSync if byte == SYNC => Response::Transition(Framer::Length),
+            Framer::WaitSync => Response::Handled,
+            Framer::Length if byte as usize > MAX_PAYLOAD => {
+                Response::Error(FrameError::TooLong(byte))
+            }
+            Framer::Length => Response::Transition(Framer::Payload {
+                buffer: [0; MAX_PAYLOAD],
+                length: byte,
+                received: 0,
+            }),
+            Framer::Payload {
+                buffer,
+                length,
+                received,
+            } => {
+                buffer[*received as usize] = byte;
+                *received += 1;
+                if received == length {
+                    Response::Transition(Framer::Checksum {
+                        buffer: *buffer,
+                        length: *length,
+                    })
+                } else {
+                    Response::Handled
+                }
+            }
+            Framer::Checksum { buffer, length } => {
+                let payload = &buffer[..*length as usize];
+                let sum = payload.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+                if sum != byte {
+                    return Response::Error(FrameError::BadChecksum);
+                }
+                link.packets += 1;
+                link.last = *buffer;
+                link.last_length = *length;
+                Response::Transition(Framer::WaitSync)
+            }
+        }
+    }
+
+    fn on_exit(&mut self, _link: &mut Link) {}
+}
+
+// Define a function feeding received bytes to the framer, dropping back to WaitSync on a bad frame
+pub fn receive(
+    framer: &mut StaticStateMachine<Framer, Link, u8, FrameError>,
+    bytes: &[u8],
+) -> Result<(), Error<Framer, FrameError>> {
+    for byte in bytes {
+        if let Err(e) = framer.process_event(byte) {
+            framer.reinit(Framer::WaitSync)?;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+// Define a machine using the default error type, a `&'static str`
+#[derive(Debug)]
+pub enum Led {
+    Off,
+    On,
+}
+
+impl StateEnum<u32, bool> for Led {
+    fn on_enter(&mut self, toggles: &mut u32) -> Response<Led> {
+        *toggles += 1;
+        Response::Handled
+    }
+
+    fn on_event(&mut self, on: &bool, _toggles: &mut u32) -> Response<Led> {
+        match (self, on) {
+            (Led::Off, true) => Response::Transition(Led::On),
+            (Led::On, false) => Response::Transition(Led::Off),
+            _ => Response::Error("the led is already in that state"),
+        }
+    }
+
+    fn on_exit(&mut self, _toggles: &mut u32) {}
+}
+
+// Define the states of a button debouncer kept by a fixed::StateMachine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Button {
+    Released,
+    Bouncing,
+    Pressed,
+}
+
+// Define the objects of the button states; Bouncing counts the samples read high in a row
+pub enum Debounce {
+    Released,
+    Bouncing { samples: u8 },
+    Pressed,
+}
+
+// Define the number of high samples needed to accept a press
+pub const STABLE_SAMPLES: u8 = 3;
+
+// Define the debouncer, with a slot for each of the three button states
+pub type Debouncer = fixed::StateMachine<Button, u32, bool, &'static str, 3>;
+
+impl FsmEnum<u32, bool> for Button {
+    type State = Debounce;
+
+    fn create(&self) -> Debounce {
+        match self {
+            Button::Released => Debounce::Released,
+            Button::Bouncing => Debounce::Bouncing { samples: 0 },
+            Button::Pressed => Debounce::Pressed,
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Stateful<Button, u32, bool> for Debounce {
+    fn on_enter(&mut self, presses: &mut u32) -> Response<Button> {
+        match self {
+            Debounce::Bouncing { samples } => *samples = 1,
+            Debounce::Pressed => *presses += 1,
+            Debounce::Released => {}
+        }
+        Response::Handled
+    }
+
+    fn on_event(&mut self, high: &bool, _presses: &mut u32) -> Response<Button> {
+        match (self, high) {
+            (Debounce::Released, true) => Response::Transition(Button::Bouncing),
+            (Debounce::Bouncing { samples }, true) => {
+                *samples += 1;
+                if *samples == STABLE_SAMPLES {
+                    Response::Transition(Button::Pressed)
+                } else {
+                    Response::Handled
+                }
+            }
+            (Debounce::Released, false) | (Debounce::Pressed, true) => Response::Handled,
+            (_, false) => Response::Transition(Button::Released),
+        }
+    }
+
+    fn on_exit(&mut self, _presses: &mut u32) {}
+}
+
+// Define a helper telling whether an error is the one returned by a state's on_event
+pub fn is_invalid_event<S, Err>(error: Error<S, Err>) -> bool {
+    matches!(error.into_kind(), ErrorKind::InvalidEvent(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn framer() -> StaticStateMachine<Framer, Link, u8, FrameError> {
+        let mut framer = StaticStateMachine::new(Link::default());
+        framer.init(Framer::WaitSync).unwrap();
+        framer
+    }
+
+    #[test]
+    fn test_frames_are_received() {
+        let mut framer = framer();
+        receive(&mut framer, &[0x00, SYNC, 3, 1, 2, 3, 6, SYNC, 0, 0]).unwrap();
+        assert_eq!(framer.get_context().packets, 2);
+        assert_eq!(framer.get_context().last_length, 0);
+        assert_eq!(framer.get_current_state(), Some(&Framer::WaitSync));
+    }
+
+    #[test]
+    fn test_bad_frames_are_rejected() {
+        let mut framer = framer();
+        let error = receive(&mut framer, &[SYNC, 2, 1, 2, 4]).unwrap_err();
+        assert_eq!(
+            error.into_kind(),
+            ErrorKind::InvalidEvent(FrameError::BadChecksum)
+        );
+        assert_eq!(framer.get_current_state(), Some(&Framer::WaitSync));
+
+        let error = receive(&mut framer, &[SYNC, 9]).unwrap_err();
+        assert_eq!(
+            error.into_kind(),
+            ErrorKind::InvalidEvent(FrameError::TooLong(9))
+        );
+
+        receive(&mut framer, &[SYNC, 1, 5, 5]).unwrap();
+        assert_eq!(framer.get_context().packets, 1);
+        assert_eq!(framer.get_context().last[0], 5);
+    }
+
+    #[test]
+    fn test_static_str_errors() {
+        let mut led = StaticStateMachine::new(0);
+        led.init(Led::Off).unwrap();
+        led.process_event(&true).unwrap();
+        let error = led.process_event(&true).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid event: the led is already in that state"
+        );
+        assert!(is_invalid_event(led.process_event(&true).unwrap_err()));
+        assert_eq!(*led.get_context(), 2);
+    }
+
+    #[test]
+    fn test_button_is_debounced() {
+        let mut button = Debouncer::new(0);
+        button.init(Button::Released).unwrap();
+        for high in [true, false, true, true, true, true, false] {
+            button.process_event(&high).unwrap();
+        }
+        assert_eq!(button.get_current_state(), Some(&Button::Released));
+        assert_eq!(*button.get_context(), 1);
+
+        // The bounce counter starts over on every press
+        for high in [true, true] {
+            button.process_event(&high).unwrap();
+        }
+        assert_eq!(button.get_current_state(), Some(&Button::Bouncing));
+        button.process_event(&true).unwrap();
+        assert_eq!(button.get_current_state(), Some(&Button::Pressed));
+        assert_eq!(*button.get_context(), 2);
+    }
+}
@@ -0,0 +1,23 @@
+// Build the crate for a Cortex-M target, which has no `std`, so that the build fails if nefsm needs
+// `std` or an allocator without its `std` feature.
+
+use std::process::Command;
+
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+#[test]
+fn test_builds_for_thumbv7em() {
+    let output = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--target", TARGET, "--target-dir"])
+        .arg(env!("CARGO_TARGET_TMPDIR"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("failed to run cargo");
+    assert!(
+        output.status.success(),
+        "the build for {} failed; the target is installed with `rustup target add {}`\n{}",
+        TARGET,
+        TARGET,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}